@@ -0,0 +1,174 @@
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use crate::pipeline::Page;
+
+/// Optional metadata written to `ComicInfo.xml`.
+#[derive(Debug, Default, Clone)]
+pub struct ComicInfo {
+    pub series: Option<String>,
+    pub number: Option<String>,
+    pub title: Option<String>,
+}
+
+/// Packs the pages into a `.cbz` archive along with a generated
+/// `ComicInfo.xml`.
+///
+/// Page names are zero-padded so that readers sorting entries by name keep
+/// them in order.
+pub(crate) fn write_cbz(
+    path: &Path,
+    pages: &[Page],
+    extension: &str,
+    info: &ComicInfo,
+) -> zip::result::ZipResult<()> {
+    let file = File::create(path)?;
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+    // The pages are already compressed, so deflating them again only costs time.
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let width = pages.len().to_string().len().max(3);
+    for (i, page) in pages.iter().enumerate() {
+        zip.start_file(format!("page_{:0width$}.{extension}", i + 1), stored)?;
+        zip.write_all(&page.bytes)?;
+    }
+    zip.start_file("ComicInfo.xml", SimpleFileOptions::default())?;
+    zip.write_all(comic_info_xml(pages, info).as_bytes())?;
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+fn comic_info_xml(pages: &[Page], info: &ComicInfo) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <ComicInfo xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n",
+    );
+    let fields = [
+        ("Title", &info.title),
+        ("Series", &info.series),
+        ("Number", &info.number),
+    ];
+    for (tag, value) in fields {
+        if let Some(value) = value {
+            let _ = writeln!(xml, "  <{tag}>{}</{tag}>", escape(value));
+        }
+    }
+    let _ = writeln!(xml, "  <PageCount>{}</PageCount>", pages.len());
+    xml.push_str("  <Pages>\n");
+    for (i, page) in pages.iter().enumerate() {
+        let _ = writeln!(
+            xml,
+            "    <Page Image=\"{i}\" ImageSize=\"{}\" ImageWidth=\"{}\" ImageHeight=\"{}\" />",
+            page.bytes.len(),
+            page.width,
+            page.height
+        );
+    }
+    xml.push_str("  </Pages>\n</ComicInfo>\n");
+    xml
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use zip::ZipArchive;
+
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{
+        job::{Input, Sort},
+        source,
+    };
+
+    fn pages(count: usize) -> Vec<Page> {
+        (0..count)
+            .map(|i| Page {
+                bytes: vec![i as u8; 4],
+                width: 10,
+                height: 20 + i as u32,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn escapes_xml() {
+        assert_eq!(
+            escape(r#"Tom & Jerry's <"Best">"#),
+            "Tom &amp; Jerry&apos;s &lt;&quot;Best&quot;&gt;"
+        );
+    }
+
+    #[test]
+    fn comic_info_lists_set_fields_and_pages() {
+        let info = ComicInfo {
+            series: Some("A & B".to_string()),
+            number: None,
+            title: Some("<Start>".to_string()),
+        };
+        let xml = comic_info_xml(&pages(2), &info);
+        assert!(xml.contains("<Title>&lt;Start&gt;</Title>"));
+        assert!(xml.contains("<Series>A &amp; B</Series>"));
+        assert!(!xml.contains("<Number>"));
+        assert!(xml.contains("<PageCount>2</PageCount>"));
+        assert!(
+            xml.contains(r#"<Page Image="1" ImageSize="4" ImageWidth="10" ImageHeight="21" />"#)
+        );
+    }
+
+    #[test]
+    fn round_trips_through_an_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chapter.cbz");
+        let written = pages(12);
+        write_cbz(&path, &written, "png", &ComicInfo::default()).unwrap();
+
+        let mut zip = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let mut xml = String::new();
+        zip.by_name("ComicInfo.xml")
+            .unwrap()
+            .read_to_string(&mut xml)
+            .unwrap();
+        assert!(xml.contains("<PageCount>12</PageCount>"));
+
+        // Zero-padded names keep the pages in order however they are sorted.
+        let read = source::sources(&Input::Archive(path), Sort::Logical).unwrap();
+        let names: Vec<_> = read.iter().map(|source| source.name().to_owned()).collect();
+        let expected: Vec<_> = (1..=12)
+            .map(|i| PathBuf::from(format!("page_{i:03}.png")))
+            .collect();
+        assert_eq!(names, expected);
+        for (source, page) in read.iter().zip(&written) {
+            let source::Source::Memory { bytes, .. } = source else {
+                panic!("archive entries are read into memory");
+            };
+            assert_eq!(bytes, &page.bytes);
+        }
+    }
+
+    #[test]
+    fn page_names_are_padded_to_the_page_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chapter.cbz");
+        write_cbz(&path, &pages(1000), "jpg", &ComicInfo::default()).unwrap();
+        let zip = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        assert!(zip.index_for_name("page_0001.jpg").is_some());
+        assert!(zip.index_for_name("page_1000.jpg").is_some());
+    }
+}
@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::job::Setting;
+
+#[derive(Error, Debug, Clone)]
+pub enum StitchError {
+    #[error("At least one input image must be provided")]
+    NoInputImages,
+    #[error("Invalid {setting}: {reason}")]
+    InvalidSetting { setting: Setting, reason: String },
+    #[error("Unable to read input directory {}: {reason}", path.display())]
+    ReadDirectory { path: PathBuf, reason: String },
+    #[error("Unable to read archive {}: {reason}", path.display())]
+    ReadArchive { path: PathBuf, reason: String },
+    #[error("{} is missing from {}", entry.display(), archive.display())]
+    MissingEntry { archive: PathBuf, entry: PathBuf },
+    #[error("None of the input images could be loaded")]
+    NoLoadableImages,
+    #[error("Unable to load {}: {reason}", path.display())]
+    ImageLoad { path: PathBuf, reason: String },
+    #[error(
+        "The stitched strip would be {width}x{height} pixels, which is too large to hold in memory"
+    )]
+    StripTooLarge { width: u32, height: u64 },
+    #[error("Invalid splitpoints: {0}")]
+    InvalidSplitpoints(String),
+    #[error("Unable to create output directory {}: {reason}", path.display())]
+    CreateOutputDirectory { path: PathBuf, reason: String },
+    #[error("Output directory {} already has files in it", path.display())]
+    OutputExists { path: PathBuf },
+    #[error("Unable to remove {} left by the last run: {reason}", path.display())]
+    CleanOutput { path: PathBuf, reason: String },
+    #[error("{}", export_summary(.0))]
+    Export(Vec<ExportError>),
+    /// Writing the output failed, and so did putting the output directory
+    /// back as it was.
+    #[error(
+        "{cause}. The output directory couldn't be put back as it was, check {}",
+        stranded.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", ")
+    )]
+    Incomplete {
+        cause: Box<StitchError>,
+        stranded: Vec<PathBuf>,
+    },
+    #[error("Stitching was cancelled")]
+    Cancelled,
+}
+
+/// A page or archive that could not be written.
+#[derive(Error, Debug, Clone)]
+#[error("Unable to export {}: {reason}", path.display())]
+pub struct ExportError {
+    /// The number of the page, counting from 1, or `None` if the archive
+    /// holding every page could not be written.
+    pub page: Option<usize>,
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+fn export_summary(errors: &[ExportError]) -> String {
+    match errors {
+        [error] => error.to_string(),
+        [first, ..] => format!("{first} (and {} more)", errors.len() - 1),
+        [] => "Unable to export".to_string(),
+    }
+}
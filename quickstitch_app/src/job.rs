@@ -0,0 +1,486 @@
+use std::{fmt, path::PathBuf};
+
+use crate::{cbz::ComicInfo, error::StitchError, naming};
+
+/// The default interval at which rows are scanned for splitpoints.
+pub const DEFAULT_SCAN_INTERVAL: usize = 5;
+/// The default sensitivity used to decide whether a row can be cut.
+pub const DEFAULT_SENSITIVITY: u8 = 220;
+/// The default height, in pixels, stitched images aim for.
+pub const DEFAULT_MAX_HEIGHT: usize = 5000;
+/// The default height, in pixels, stitched images must at least have.
+pub const DEFAULT_MIN_HEIGHT: usize = 0;
+/// The largest width or height, in pixels, a JPEG image can have.
+pub const JPEG_MAX_DIMENSION: usize = 65_535;
+/// The largest width or height, in pixels, a WebP image can have.
+pub const WEBP_MAX_DIMENSION: usize = 16_383;
+
+/// How input images are ordered by file name.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    /// Numbers are compared by value: `["8", "9", "10", "11"]`.
+    #[default]
+    Natural,
+    /// Names are compared character by character: `["10", "11", "8", "9"]`.
+    Logical,
+}
+
+/// The format stitched pages are exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Png,
+    Webp,
+    /// JPEG with the given quality, saved with a `.jpg` extension.
+    Jpg(u8),
+    /// JPEG with the given quality, saved with a `.jpeg` extension.
+    Jpeg(u8),
+}
+
+impl OutputFormat {
+    /// The file extension used when exporting in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Jpg(_) => "jpg",
+            OutputFormat::Jpeg(_) => "jpeg",
+        }
+    }
+
+    /// The largest width or height, in pixels, a page can have in this
+    /// format.
+    pub fn max_dimension(&self) -> usize {
+        match self {
+            OutputFormat::Png => u32::MAX as usize,
+            OutputFormat::Webp => WEBP_MAX_DIMENSION,
+            OutputFormat::Jpg(_) | OutputFormat::Jpeg(_) => JPEG_MAX_DIMENSION,
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OutputFormat::Png => "PNG",
+            OutputFormat::Webp => "WebP",
+            OutputFormat::Jpg(_) | OutputFormat::Jpeg(_) => "JPEG",
+        })
+    }
+}
+
+/// What to do when the output directory already has files in it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OnExisting {
+    /// Fail without writing anything.
+    Error,
+    /// Replace files with the same names as the new pages, leaving any others.
+    #[default]
+    Overwrite,
+    /// Remove the files the last run recorded in the directory before writing
+    /// the new pages.
+    Clean,
+    /// Write to the first of `<output>-2`, `<output>-3`, ... that is empty or
+    /// doesn't exist yet.
+    NewDir,
+}
+
+impl OnExisting {
+    /// Whether stitching into the same output again replaces the last run's
+    /// pages, which rebuilding whenever the input changes relies on.
+    /// [`OnExisting::Error`] would fail every rebuild and
+    /// [`OnExisting::NewDir`] would make another directory each time.
+    pub fn allows_rebuilds(&self) -> bool {
+        matches!(self, OnExisting::Overwrite | OnExisting::Clean)
+    }
+}
+
+/// Where the images of a job come from.
+#[derive(Debug, Clone)]
+pub enum Input {
+    /// Every supported image in a directory, sorted by file name.
+    Directory(PathBuf),
+    /// Every supported image in a `.cbz`/`.zip` archive, sorted by file name.
+    Archive(PathBuf),
+    /// The given images, in order.
+    Images(Vec<PathBuf>),
+    /// The given entries of an archive, in order.
+    ArchiveEntries(PathBuf, Vec<PathBuf>),
+}
+
+impl Input {
+    /// The name of the input directory or archive, used to name packed output.
+    pub fn name(&self) -> String {
+        let path = match self {
+            Input::Directory(path) | Input::Archive(path) | Input::ArchiveEntries(path, _) => path,
+            Input::Images(_) => return "stitched".to_string(),
+        };
+        path.file_stem().map_or("stitched".to_string(), |stem| {
+            stem.to_string_lossy().into_owned()
+        })
+    }
+
+    /// The archive the images are read from, if any.
+    pub fn archive(&self) -> Option<&PathBuf> {
+        match self {
+            Input::Archive(path) | Input::ArchiveEntries(path, _) => Some(path),
+            Input::Directory(_) | Input::Images(_) => None,
+        }
+    }
+}
+
+/// A setting of a [`StitchJob`] that can be rejected by
+/// [`StitchJob::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    Width,
+    MaxHeight,
+    MinHeight,
+    ScanInterval,
+    Quality,
+    NameTemplate,
+}
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Setting::Width => "width",
+            Setting::MaxHeight => "max height",
+            Setting::MinHeight => "min height",
+            Setting::ScanInterval => "scan interval",
+            Setting::Quality => "quality",
+            Setting::NameTemplate => "name template",
+        })
+    }
+}
+
+/// Everything needed for a single stitching run.
+#[derive(Debug, Clone)]
+pub struct StitchJob {
+    pub input: Input,
+    /// Only used for [`Input::Directory`] and [`Input::Archive`].
+    pub sort: Sort,
+    /// Skip images that fail to load instead of failing the job.
+    pub ignore_unloadable: bool,
+    pub output: PathBuf,
+    pub on_existing: OnExisting,
+    pub format: OutputFormat,
+    /// When set, pages are packed into a `.cbz` with this metadata instead of
+    /// being written as loose files.
+    pub comic_info: Option<ComicInfo>,
+    /// How loose pages are named, see [`PageName`](crate::PageName). Pages
+    /// packed into a `.cbz` keep their own names.
+    pub name_template: String,
+    /// The width every image is resized to. Defaults to the narrowest input.
+    pub width: Option<u32>,
+    pub max_height: usize,
+    pub min_height: usize,
+    pub scan_interval: usize,
+    pub sensitivity: u8,
+    /// Draw the chosen and skipped cut rows onto the exported pages.
+    pub debug: bool,
+}
+
+impl StitchJob {
+    /// Checks that the settings make sense together, without touching the
+    /// input or output.
+    pub fn validate(&self) -> Result<(), StitchError> {
+        if let Input::Images(images) | Input::ArchiveEntries(_, images) = &self.input
+            && images.is_empty()
+        {
+            return Err(StitchError::NoInputImages);
+        }
+        self.validate_settings()
+    }
+
+    /// Checks every setting that doesn't depend on the input, so that they can
+    /// be checked once for many inputs.
+    pub fn validate_settings(&self) -> Result<(), StitchError> {
+        let invalid = |setting, reason: &str| {
+            Err(StitchError::InvalidSetting {
+                setting,
+                reason: reason.to_string(),
+            })
+        };
+        let limit = self.format.max_dimension();
+        let too_large = format!(
+            "must not be greater than {limit} pixels for {}",
+            self.format
+        );
+        if self.width == Some(0) {
+            return invalid(Setting::Width, "must be greater than 0");
+        }
+        if self.width.is_some_and(|width| width as usize > limit) {
+            return invalid(Setting::Width, &too_large);
+        }
+        if self.max_height == 0 {
+            return invalid(Setting::MaxHeight, "must be greater than 0");
+        }
+        if self.max_height > limit {
+            return invalid(Setting::MaxHeight, &too_large);
+        }
+        if self.min_height > limit {
+            return invalid(Setting::MinHeight, &too_large);
+        }
+        if self.min_height > self.max_height {
+            return invalid(
+                Setting::MinHeight,
+                &format!(
+                    "must not be greater than the max height ({})",
+                    self.max_height
+                ),
+            );
+        }
+        if self.scan_interval == 0 {
+            return invalid(Setting::ScanInterval, "must be greater than 0");
+        }
+        if let OutputFormat::Jpg(quality) | OutputFormat::Jpeg(quality) = self.format
+            && !(1..=100).contains(&quality)
+        {
+            return invalid(Setting::Quality, "must be between 1 and 100");
+        }
+        if let Err(reason) = naming::check_name_template(&self.name_template) {
+            return invalid(Setting::NameTemplate, &reason);
+        }
+        Ok(())
+    }
+
+    /// Checks that splitpoints, whether found or edited by hand, cut a strip
+    /// `height` rows tall into pages this job's format can hold.
+    ///
+    /// Every splitpoint must be greater than the one before it, so that each
+    /// page is at least one row tall, and less than the strip height.
+    pub fn validate_splitpoints(&self, cuts: &[usize], height: usize) -> Result<(), StitchError> {
+        let invalid = |reason: String| Err(StitchError::InvalidSplitpoints(reason));
+        let limit = self.format.max_dimension();
+        let rows: Vec<usize> = [0]
+            .into_iter()
+            .chain(cuts.iter().copied())
+            .chain([height])
+            .collect();
+        for (i, page) in rows.windows(2).enumerate() {
+            let (start, end) = (page[0], page[1]);
+            if i < cuts.len() && end <= start {
+                return invalid(format!("{end} must be greater than {start}"));
+            }
+            if i < cuts.len() && end >= height {
+                return invalid(format!(
+                    "{end} must be less than the strip height ({height})"
+                ));
+            }
+            if end - start > limit {
+                return invalid(format!(
+                    "page {} would be {} pixels tall, more than {limit} pixels for {}",
+                    i + 1,
+                    end - start,
+                    self.format
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(format: OutputFormat) -> StitchJob {
+        StitchJob {
+            input: Input::Images(vec![PathBuf::from("1.png")]),
+            sort: Sort::Natural,
+            ignore_unloadable: true,
+            output: PathBuf::from("stitched"),
+            on_existing: OnExisting::Overwrite,
+            format,
+            comic_info: None,
+            name_template: naming::DEFAULT_NAME_TEMPLATE.to_string(),
+            width: None,
+            max_height: DEFAULT_MAX_HEIGHT,
+            min_height: DEFAULT_MIN_HEIGHT,
+            scan_interval: DEFAULT_SCAN_INTERVAL,
+            sensitivity: DEFAULT_SENSITIVITY,
+            debug: false,
+        }
+    }
+
+    fn splitpoints_error(format: OutputFormat, cuts: &[usize], height: usize) -> String {
+        match job(format).validate_splitpoints(cuts, height) {
+            Err(StitchError::InvalidSplitpoints(reason)) => reason,
+            result => panic!("expected invalid splitpoints, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn accepts_sorted_splitpoints_inside_the_strip() {
+        let job = job(OutputFormat::Png);
+        assert!(job.validate_splitpoints(&[], 100).is_ok());
+        assert!(job.validate_splitpoints(&[1, 50, 99], 100).is_ok());
+    }
+
+    #[test]
+    fn rejects_unsorted_and_out_of_range_splitpoints() {
+        let png = OutputFormat::Png;
+        assert_eq!(
+            splitpoints_error(png, &[50, 40], 100),
+            "40 must be greater than 50"
+        );
+        assert_eq!(
+            splitpoints_error(png, &[50, 50], 100),
+            "50 must be greater than 50"
+        );
+        assert_eq!(
+            splitpoints_error(png, &[0], 100),
+            "0 must be greater than 0"
+        );
+        assert_eq!(
+            splitpoints_error(png, &[100], 100),
+            "100 must be less than the strip height (100)"
+        );
+    }
+
+    #[test]
+    fn rejects_pages_too_tall_for_the_format() {
+        let height = WEBP_MAX_DIMENSION * 2;
+        let webp = OutputFormat::Webp;
+        assert!(
+            job(webp)
+                .validate_splitpoints(&[WEBP_MAX_DIMENSION], height)
+                .is_ok()
+        );
+        assert_eq!(
+            splitpoints_error(webp, &[WEBP_MAX_DIMENSION - 1], height),
+            format!(
+                "page 2 would be {} pixels tall, more than {WEBP_MAX_DIMENSION} pixels for WebP",
+                WEBP_MAX_DIMENSION + 1
+            )
+        );
+    }
+
+    /// The setting `validate_settings` rejects, if any.
+    fn rejected(job: StitchJob) -> Option<Setting> {
+        match job.validate_settings() {
+            Ok(()) => None,
+            Err(StitchError::InvalidSetting { setting, .. }) => Some(setting),
+            Err(e) => panic!("expected an invalid setting, got {e}"),
+        }
+    }
+
+    #[test]
+    fn width_must_fit_the_format() {
+        for format in [OutputFormat::Jpg(100), OutputFormat::Webp] {
+            let limit = format.max_dimension() as u32;
+            let with_width = |width| StitchJob {
+                width: Some(width),
+                ..job(format)
+            };
+            assert_eq!(rejected(with_width(0)), Some(Setting::Width));
+            assert_eq!(rejected(with_width(1)), None);
+            assert_eq!(rejected(with_width(limit - 1)), None);
+            assert_eq!(rejected(with_width(limit)), None);
+            assert_eq!(rejected(with_width(limit + 1)), Some(Setting::Width));
+        }
+        assert_eq!(
+            rejected(StitchJob {
+                width: Some(u32::MAX),
+                ..job(OutputFormat::Png)
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn heights_must_fit_the_format() {
+        for format in [
+            OutputFormat::Jpg(100),
+            OutputFormat::Webp,
+            OutputFormat::Png,
+        ] {
+            let limit = format.max_dimension();
+            let with_heights = |max_height, min_height| StitchJob {
+                max_height,
+                min_height,
+                ..job(format)
+            };
+            assert_eq!(rejected(with_heights(0, 0)), Some(Setting::MaxHeight));
+            assert_eq!(rejected(with_heights(1, 0)), None);
+            assert_eq!(rejected(with_heights(limit - 1, 0)), None);
+            assert_eq!(rejected(with_heights(limit, limit)), None);
+            assert_eq!(
+                rejected(with_heights(limit + 1, 0)),
+                Some(Setting::MaxHeight)
+            );
+            assert_eq!(
+                rejected(with_heights(limit, limit + 1)),
+                Some(Setting::MinHeight)
+            );
+        }
+    }
+
+    #[test]
+    fn min_height_must_not_exceed_max_height() {
+        let with_heights = |max_height, min_height| StitchJob {
+            max_height,
+            min_height,
+            ..job(OutputFormat::Png)
+        };
+        assert_eq!(rejected(with_heights(1000, 999)), None);
+        assert_eq!(rejected(with_heights(1000, 1000)), None);
+        assert_eq!(rejected(with_heights(1000, 1001)), Some(Setting::MinHeight));
+    }
+
+    #[test]
+    fn quality_must_be_between_1_and_100() {
+        for quality_format in [OutputFormat::Jpg, OutputFormat::Jpeg] {
+            let with_quality = |quality| job(quality_format(quality));
+            assert_eq!(rejected(with_quality(0)), Some(Setting::Quality));
+            assert_eq!(rejected(with_quality(1)), None);
+            assert_eq!(rejected(with_quality(2)), None);
+            assert_eq!(rejected(with_quality(99)), None);
+            assert_eq!(rejected(with_quality(100)), None);
+            assert_eq!(rejected(with_quality(101)), Some(Setting::Quality));
+        }
+    }
+
+    #[test]
+    fn scan_interval_must_be_positive() {
+        let with_interval = |scan_interval| StitchJob {
+            scan_interval,
+            ..job(OutputFormat::Png)
+        };
+        assert_eq!(rejected(with_interval(0)), Some(Setting::ScanInterval));
+        assert_eq!(rejected(with_interval(1)), None);
+    }
+
+    #[test]
+    fn any_sensitivity_is_accepted() {
+        // A `u8` is always a valid sensitivity, so there is no bound to cross.
+        for sensitivity in [0, 1, DEFAULT_SENSITIVITY, u8::MAX - 1, u8::MAX] {
+            let job = StitchJob {
+                sensitivity,
+                ..job(OutputFormat::Png)
+            };
+            assert_eq!(rejected(job), None);
+        }
+    }
+
+    #[test]
+    fn name_template_must_be_usable() {
+        let with_template = |template: &str| StitchJob {
+            name_template: template.to_string(),
+            ..job(OutputFormat::Png)
+        };
+        assert_eq!(rejected(with_template("{page:03}.{ext}")), None);
+        assert_eq!(
+            rejected(with_template("cover.{ext}")),
+            Some(Setting::NameTemplate)
+        );
+    }
+
+    #[test]
+    fn only_replacing_policies_allow_rebuilds() {
+        assert!(OnExisting::Overwrite.allows_rebuilds());
+        assert!(OnExisting::Clean.allows_rebuilds());
+        assert!(!OnExisting::Error.allows_rebuilds());
+        assert!(!OnExisting::NewDir.allows_rebuilds());
+    }
+}
@@ -0,0 +1,27 @@
+//! Stitching shared by the Quickstitch CLI and GUI.
+//!
+//! A [`StitchJob`] describes a single run and is validated before anything is
+//! loaded. [`run`] takes a job from start to finish, while [`load`],
+//! [`find_splitpoints`] and [`export`] expose each phase for frontends that
+//! need to step in between them.
+
+mod cbz;
+mod error;
+mod job;
+mod naming;
+mod pipeline;
+mod runner;
+mod source;
+
+pub use cbz::ComicInfo;
+pub use error::{ExportError, StitchError};
+pub use job::{
+    DEFAULT_MAX_HEIGHT, DEFAULT_MIN_HEIGHT, DEFAULT_SCAN_INTERVAL, DEFAULT_SENSITIVITY, Input,
+    JPEG_MAX_DIMENSION, OnExisting, OutputFormat, Setting, Sort, StitchJob, WEBP_MAX_DIMENSION,
+};
+pub use naming::{DEFAULT_NAME_TEMPLATE, PageName, check_name_template, today};
+pub use pipeline::{Skipped, Splitpoints, Strip};
+pub use runner::{
+    CancelToken, Outcome, Progress, export, find_splitpoints, has_existing_output, load, run,
+};
+pub use source::{find_chapters, is_archive, is_image, sort_by_name};
@@ -0,0 +1,216 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Names pages `page_1.jpg`, `page_2.jpg` and so on.
+pub const DEFAULT_NAME_TEMPLATE: &str = "page_{page}.{ext}";
+
+/// Characters that separate directories or aren't allowed in file names on
+/// Windows.
+const FORBIDDEN: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// The values a name template's placeholders are filled in with.
+///
+/// Placeholders are written as `{name}`, and numbers can be zero-padded with
+/// `{name:03}`. `{{` and `}}` stand for literal braces.
+#[derive(Debug, Clone)]
+pub struct PageName {
+    /// `{page}`: the page number, counting from 1.
+    pub page: usize,
+    /// `{pages}`: the number of pages in the chapter.
+    pub pages: usize,
+    /// `{chapter}`: the name of the input directory or archive.
+    pub chapter: String,
+    /// `{first}`: the file stem of the first input image.
+    pub first: String,
+    /// `{date}`: the date of the run, as `YYYY-MM-DD` in UTC.
+    pub date: String,
+    /// `{ext}`: the extension of the output format.
+    pub ext: String,
+}
+
+impl PageName {
+    /// Fills in the template, or explains why it can't be used.
+    pub fn render(&self, template: &str) -> Result<String, String> {
+        let mut name = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    name.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    name.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let Some(end) = rest.find('}') else {
+                        return Err("has a \"{\" that is never closed".to_string());
+                    };
+                    name.push_str(&self.placeholder(&rest[..end])?);
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => return Err("has a \"}\" that was never opened".to_string()),
+                c => name.push(c),
+            }
+        }
+        Ok(name)
+    }
+
+    fn placeholder(&self, placeholder: &str) -> Result<String, String> {
+        let (key, width) = match placeholder.split_once(':') {
+            Some((key, width)) => match width.parse::<usize>() {
+                Ok(width) => (key, Some(width)),
+                Err(_) => return Err(format!("has an invalid width in \"{{{placeholder}}}\"")),
+            },
+            None => (placeholder, None),
+        };
+        let number = match key {
+            "page" => self.page,
+            "pages" => self.pages,
+            "chapter" | "first" | "date" | "ext" if width.is_some() => {
+                return Err(format!("can only pad numbers, not \"{{{key}}}\""));
+            }
+            "chapter" => return Ok(self.chapter.clone()),
+            "first" => return Ok(self.first.clone()),
+            "date" => return Ok(self.date.clone()),
+            "ext" => return Ok(self.ext.clone()),
+            _ => return Err(format!("has an unknown placeholder \"{{{key}}}\"")),
+        };
+        Ok(format!("{number:0width$}", width = width.unwrap_or(0)))
+    }
+}
+
+/// Checks that a template can be filled in and gives every page its own file
+/// name directly inside the output directory.
+pub fn check_name_template(template: &str) -> Result<(), String> {
+    let example = PageName {
+        page: 1,
+        pages: 1,
+        chapter: String::new(),
+        first: String::new(),
+        date: String::new(),
+        ext: String::new(),
+    };
+    let name = example.render(template)?;
+    // Rendering the page number alone shows whether it is part of the name.
+    let numbered = PageName {
+        page: 2,
+        ..example.clone()
+    };
+    if numbered.render(template)? == name {
+        return Err("must include the page number with \"{page}\"".to_string());
+    }
+    // The `:` of a padded placeholder is fine, so the rendered name is checked.
+    if let Some(c) = name.chars().find(|c| FORBIDDEN.contains(c)) {
+        let forbidden: Vec<String> = FORBIDDEN.iter().map(char::to_string).collect();
+        return Err(format!(
+            "must not contain \"{c}\" (or any of {})",
+            forbidden.join(" ")
+        ));
+    }
+    Ok(())
+}
+
+/// Today's date as `YYYY-MM-DD`, in UTC.
+pub fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    // Converts days since the epoch to a civil date, see
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(page: usize, pages: usize) -> PageName {
+        PageName {
+            page,
+            pages,
+            chapter: "Chapter 7".to_string(),
+            first: "001".to_string(),
+            date: "2024-02-29".to_string(),
+            ext: "jpg".to_string(),
+        }
+    }
+
+    #[test]
+    fn renders_every_placeholder() {
+        let template = "{chapter} - {first} - {date} - {page} of {pages}.{ext}";
+        assert_eq!(
+            name(3, 12).render(template).unwrap(),
+            "Chapter 7 - 001 - 2024-02-29 - 3 of 12.jpg"
+        );
+        assert_eq!(
+            name(1, 1).render(DEFAULT_NAME_TEMPLATE).unwrap(),
+            "page_1.jpg"
+        );
+    }
+
+    #[test]
+    fn pads_numbers() {
+        assert_eq!(
+            name(7, 120).render("{page:03}_{pages:5}").unwrap(),
+            "007_00120"
+        );
+        // Numbers wider than the padding are left whole.
+        assert_eq!(name(1234, 1234).render("{page:2}").unwrap(), "1234");
+    }
+
+    #[test]
+    fn escapes_braces() {
+        assert_eq!(name(2, 2).render("{{{page}}}").unwrap(), "{2}");
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        let error = |template| name(1, 1).render(template).unwrap_err();
+        assert_eq!(error("{page"), "has a \"{\" that is never closed");
+        assert_eq!(error("page}"), "has a \"}\" that was never opened");
+        assert_eq!(error("{volume}"), "has an unknown placeholder \"{volume}\"");
+        assert_eq!(error("{page:x}"), "has an invalid width in \"{page:x}\"");
+        assert_eq!(
+            error("{chapter:3}"),
+            "can only pad numbers, not \"{chapter}\""
+        );
+    }
+
+    #[test]
+    fn checks_templates() {
+        assert_eq!(check_name_template(DEFAULT_NAME_TEMPLATE), Ok(()));
+        assert_eq!(check_name_template("{chapter}_{page:03}.{ext}"), Ok(()));
+        assert_eq!(
+            check_name_template("{chapter}.{ext}"),
+            Err("must include the page number with \"{page}\"".to_string())
+        );
+        assert!(check_name_template("{page").is_err());
+    }
+
+    #[test]
+    fn rejects_forbidden_characters() {
+        for c in FORBIDDEN {
+            let error = check_name_template(&format!("page{c}{{page}}")).unwrap_err();
+            assert!(
+                error.starts_with(&format!("must not contain \"{c}\"")),
+                "{error}"
+            );
+        }
+    }
+}
@@ -0,0 +1,396 @@
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use image::{
+    DynamicImage, GenericImage, ImageError, ImageResult, Rgb, RgbImage,
+    codecs::jpeg::JpegEncoder,
+    error::{ParameterError, ParameterErrorKind},
+    imageops::{self, FilterType},
+};
+use rayon::prelude::*;
+
+use crate::{
+    error::StitchError,
+    job::OutputFormat,
+    runner::{CancelToken, Progress},
+    source::Source,
+};
+
+/// The input images stacked into a single strip.
+#[derive(Debug)]
+pub struct Strip {
+    pub image: RgbImage,
+    /// The names of the inputs that made it into the strip, in order.
+    pub inputs: Vec<PathBuf>,
+    /// The row of the strip at which each input starts.
+    pub offsets: Vec<usize>,
+    /// The inputs that were left out because they couldn't be loaded.
+    pub skipped: Vec<Skipped>,
+}
+
+/// An input that couldn't be loaded and was left out of the strip.
+#[derive(Debug, Clone)]
+pub struct Skipped {
+    pub input: PathBuf,
+    pub reason: String,
+}
+
+impl Strip {
+    /// Finds the input that the given row of the strip came from, along with
+    /// the row within that input.
+    pub fn locate(&self, row: usize) -> (&Path, usize) {
+        let i = self.offsets.partition_point(|&offset| offset <= row) - 1;
+        (&self.inputs[i], row - self.offsets[i])
+    }
+}
+
+/// Loads the given images and stacks them into a single strip.
+///
+/// Every image is resized to `width`, or to the narrowest input when no width
+/// is given, keeping its aspect ratio.
+pub(crate) fn load_images(
+    sources: &[Source],
+    width: Option<u32>,
+    ignore_unloadable: bool,
+    cancel: &CancelToken,
+    progress: &(dyn Fn(Progress) + Sync),
+) -> Result<Strip, StitchError> {
+    let total = sources.len();
+    let loaded = AtomicUsize::new(0);
+    progress(Progress::Loading { loaded: 0, total });
+    let images = sources
+        .par_iter()
+        .map(|source| {
+            if cancel.is_cancelled() {
+                return Err(StitchError::Cancelled);
+            }
+            let name = source.name().to_path_buf();
+            let image = source.decode().map(DynamicImage::into_rgb8);
+            let loaded = loaded.fetch_add(1, Ordering::Relaxed) + 1;
+            progress(Progress::Loading { loaded, total });
+            match image {
+                Ok(image) => Ok(Ok((name, image))),
+                Err(e) if ignore_unloadable => Ok(Err(Skipped {
+                    input: name,
+                    reason: e.to_string(),
+                })),
+                Err(e) => Err(StitchError::ImageLoad {
+                    path: name,
+                    reason: e.to_string(),
+                }),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let (images, skipped): (Vec<_>, Vec<_>) = images.into_iter().partition(Result::is_ok);
+    let images: Vec<(PathBuf, RgbImage)> = images.into_iter().flatten().collect();
+    let skipped: Vec<Skipped> = skipped.into_iter().filter_map(Result::err).collect();
+
+    if images.is_empty() {
+        return Err(StitchError::NoLoadableImages);
+    }
+    let width = width.unwrap_or_else(|| {
+        images
+            .iter()
+            .map(|(_, image)| image.width())
+            .min()
+            .unwrap_or(1)
+    });
+    let (inputs, images): (Vec<PathBuf>, Vec<RgbImage>) = images
+        .into_par_iter()
+        .map(|(name, image)| (name, resize_to_width(image, width)))
+        .unzip();
+
+    let heights: Vec<u32> = images.iter().map(RgbImage::height).collect();
+    let mut strip = allocate_strip(width, &heights)?;
+    let mut offsets = Vec::with_capacity(images.len());
+    let mut y = 0;
+    for image in images {
+        strip
+            .copy_from(&image, 0, y)
+            .expect("every image is resized to the strip width");
+        offsets.push(y as usize);
+        y += image.height();
+    }
+    Ok(Strip {
+        image: strip,
+        inputs,
+        offsets,
+        skipped,
+    })
+}
+
+/// Allocates a strip tall enough for every image, failing instead of
+/// overflowing or aborting when a long chapter doesn't fit in memory.
+fn allocate_strip(width: u32, heights: &[u32]) -> Result<RgbImage, StitchError> {
+    let too_large = || StitchError::StripTooLarge {
+        width,
+        height: heights.iter().copied().map(u64::from).sum(),
+    };
+    let height = heights
+        .iter()
+        .try_fold(0u32, |total, &height| total.checked_add(height))
+        .ok_or_else(too_large)?;
+    let len = u64::from(width)
+        .checked_mul(u64::from(height))
+        .and_then(|pixels| pixels.checked_mul(3))
+        .and_then(|len| usize::try_from(len).ok())
+        .ok_or_else(too_large)?;
+    let mut buffer = Vec::new();
+    buffer.try_reserve_exact(len).map_err(|_| too_large())?;
+    buffer.resize(len, 0);
+    Ok(RgbImage::from_raw(width, height, buffer).expect("the buffer fits the strip exactly"))
+}
+
+fn resize_to_width(image: RgbImage, width: u32) -> RgbImage {
+    if image.width() == width {
+        return image;
+    }
+    let height = (image.height() as u64 * width as u64 / image.width() as u64).max(1) as u32;
+    imageops::resize(&image, width, height, FilterType::Lanczos3)
+}
+
+/// Splitpoints chosen for a strip, along with the rows that were scanned and
+/// rejected along the way (only used to draw debug lines).
+#[derive(Debug, Clone, Default)]
+pub struct Splitpoints {
+    pub cuts: Vec<usize>,
+    pub skipped: Vec<usize>,
+}
+
+/// Finds the rows at which the strip should be cut.
+///
+/// Starting `max_height` pixels below the previous cut, every `scan_interval`th
+/// row is checked, moving upwards, until one is found whose neighbouring pixels
+/// differ by no more than `255 - sensitivity`. If no such row exists above
+/// `min_height`, the strip is cut at `max_height`.
+pub(crate) fn find_splitpoints(
+    strip: &RgbImage,
+    max_height: usize,
+    min_height: usize,
+    scan_interval: usize,
+    sensitivity: u8,
+) -> Splitpoints {
+    let height = strip.height() as usize;
+    let max_height = max_height.max(1);
+    let scan_interval = scan_interval.max(1);
+    let threshold = 255 - sensitivity;
+
+    let mut cuts = vec![];
+    let mut skipped = vec![];
+    let mut start = 0;
+    while height - start > max_height {
+        let mut row = start + max_height;
+        let cut = loop {
+            if row <= start + min_height {
+                break start + max_height;
+            }
+            if row_is_uniform(strip, row, threshold) {
+                break row;
+            }
+            skipped.push(row);
+            match row.checked_sub(scan_interval) {
+                Some(next) => row = next,
+                None => break start + max_height,
+            }
+        };
+        cuts.push(cut);
+        start = cut;
+    }
+    Splitpoints { cuts, skipped }
+}
+
+fn row_is_uniform(strip: &RgbImage, row: usize, threshold: u8) -> bool {
+    let luma = |pixel: &Rgb<u8>| {
+        let [r, g, b] = pixel.0;
+        ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as i16
+    };
+    let row = row as u32;
+    (1..strip.width()).all(|x| {
+        let diff = luma(strip.get_pixel(x, row)) - luma(strip.get_pixel(x - 1, row));
+        diff.unsigned_abs() <= threshold as u16
+    })
+}
+
+/// A stitched page, encoded and ready to be written.
+pub struct Page {
+    pub bytes: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Crops the rows `start..end` out of the strip and encodes them, failing if
+/// they don't cover at least one row of it.
+///
+/// In debug mode, the selected cut is drawn in red at the bottom of the page
+/// and skipped rows are drawn in light blue.
+pub(crate) fn encode_page(
+    strip: &RgbImage,
+    start: usize,
+    end: usize,
+    skipped: &[usize],
+    format: &OutputFormat,
+    debug: bool,
+) -> ImageResult<Page> {
+    if start >= end || end > strip.height() as usize {
+        return Err(ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::Generic(format!("rows {start} to {end} are not part of the strip")),
+        )));
+    }
+    let mut page =
+        imageops::crop_imm(strip, 0, start as u32, strip.width(), (end - start) as u32).to_image();
+    if debug {
+        for &row in skipped.iter().filter(|&&row| row > start && row < end) {
+            draw_line(&mut page, row - start, Rgb([128, 200, 255]));
+        }
+        draw_line(&mut page, end - start - 1, Rgb([255, 0, 0]));
+    }
+    let mut bytes = Cursor::new(vec![]);
+    match format {
+        OutputFormat::Jpg(quality) | OutputFormat::Jpeg(quality) => {
+            page.write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, *quality))?
+        }
+        OutputFormat::Png => page.write_to(&mut bytes, image::ImageFormat::Png)?,
+        OutputFormat::Webp => page.write_to(&mut bytes, image::ImageFormat::WebP)?,
+    }
+    Ok(Page {
+        bytes: bytes.into_inner(),
+        width: page.width(),
+        height: page.height(),
+    })
+}
+
+fn draw_line(page: &mut RgbImage, row: usize, color: Rgb<u8>) {
+    for x in 0..page.width() {
+        page.put_pixel(x, row as u32, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 8;
+
+    /// A strip whose rows alternate black and white pixels, which can never
+    /// be cut, except for the given rows, which are a single grey.
+    fn strip(height: u32, uniform: &[u32]) -> RgbImage {
+        RgbImage::from_fn(WIDTH, height, |x, y| match uniform.contains(&y) {
+            true => Rgb([128, 128, 128]),
+            false if x % 2 == 0 => Rgb([0, 0, 0]),
+            false => Rgb([255, 255, 255]),
+        })
+    }
+
+    fn png(image: &DynamicImage) -> Source {
+        let mut bytes = Cursor::new(vec![]);
+        image.write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+        Source::Memory {
+            name: PathBuf::from(format!("{}x{}.png", image.width(), image.height())),
+            bytes: bytes.into_inner(),
+        }
+    }
+
+    fn load(sources: &[Source], width: Option<u32>) -> Result<Strip, StitchError> {
+        load_images(sources, width, true, &CancelToken::default(), &|_| {})
+    }
+
+    #[test]
+    fn short_strip_is_not_cut() {
+        let splitpoints = find_splitpoints(&strip(100, &[]), 100, 0, 5, 220);
+        assert!(splitpoints.cuts.is_empty());
+        assert!(splitpoints.skipped.is_empty());
+    }
+
+    #[test]
+    fn cuts_at_the_lowest_uniform_row_scanning_upwards() {
+        let splitpoints = find_splitpoints(&strip(100, &[30, 70]), 40, 0, 5, 220);
+        assert_eq!(splitpoints.cuts, [30, 70]);
+        assert_eq!(splitpoints.skipped, [40, 35]);
+    }
+
+    #[test]
+    fn only_rows_on_the_scan_interval_are_checked() {
+        let splitpoints = find_splitpoints(&strip(100, &[33]), 40, 0, 5, 220);
+        assert_eq!(splitpoints.cuts, [40, 80]);
+    }
+
+    #[test]
+    fn falls_back_to_max_height_above_min_height() {
+        let splitpoints = find_splitpoints(&strip(100, &[10]), 40, 20, 5, 220);
+        assert_eq!(splitpoints.cuts, [40, 80]);
+        assert_eq!(splitpoints.skipped, [40, 35, 30, 25, 80, 75, 70, 65]);
+    }
+
+    #[test]
+    fn sensitivity_sets_the_largest_allowed_luma_difference() {
+        // Neighbouring pixels differ in luma by exactly 30.
+        let gradient = RgbImage::from_fn(WIDTH, 100, |x, _| {
+            let value = (x * 30) as u8;
+            Rgb([value, value, value])
+        });
+        let loose = find_splitpoints(&gradient, 60, 0, 5, 225);
+        assert_eq!(loose.cuts, [60]);
+        assert!(loose.skipped.is_empty());
+        // 255 - 226 = 29, so every row is rejected and the cut falls back.
+        let strict = find_splitpoints(&gradient, 60, 0, 5, 226);
+        assert_eq!(strict.cuts, [60]);
+        assert_eq!(strict.skipped.len(), 12);
+    }
+
+    #[test]
+    fn images_are_stacked_at_the_narrowest_width_without_alpha() {
+        let wide = DynamicImage::ImageRgb8(RgbImage::from_pixel(20, 10, Rgb([255, 0, 0])));
+        let narrow = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            10,
+            30,
+            image::Rgba([0, 0, 255, 0]),
+        ));
+        let strip = load(&[png(&wide), png(&narrow)], None).unwrap();
+        assert_eq!(strip.image.dimensions(), (10, 35));
+        assert_eq!(strip.offsets, [0, 5]);
+        assert_eq!(strip.locate(4).1, 4);
+        assert_eq!(strip.locate(5), (Path::new("10x30.png"), 0));
+        // Transparent pixels keep their colour rather than being blended.
+        assert_eq!(*strip.image.get_pixel(0, 20), Rgb([0, 0, 255]));
+    }
+
+    #[test]
+    fn fixed_width_keeps_the_aspect_ratio() {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(40, 100));
+        let strip = load(&[png(&image)], Some(20)).unwrap();
+        assert_eq!(strip.image.dimensions(), (20, 50));
+    }
+
+    #[test]
+    fn unloadable_images_are_skipped() {
+        let broken = Source::Memory {
+            name: PathBuf::from("broken.png"),
+            bytes: vec![1, 2, 3],
+        };
+        let image = DynamicImage::ImageRgb8(RgbImage::new(10, 10));
+        let strip = load(&[broken, png(&image)], None).unwrap();
+        assert_eq!(strip.inputs, [PathBuf::from("10x10.png")]);
+        assert_eq!(strip.skipped[0].input, PathBuf::from("broken.png"));
+    }
+
+    #[test]
+    fn pages_outside_the_strip_are_not_encoded() {
+        let strip = strip(100, &[]);
+        for (start, end) in [(50, 40), (50, 50), (90, 101)] {
+            assert!(encode_page(&strip, start, end, &[], &OutputFormat::Png, false).is_err());
+        }
+        let page = encode_page(&strip, 90, 100, &[], &OutputFormat::Png, true).unwrap();
+        assert_eq!((page.width, page.height), (WIDTH, 10));
+    }
+
+    #[test]
+    fn strips_taller_than_u32_are_rejected() {
+        assert!(matches!(
+            allocate_strip(1, &[u32::MAX, 1]),
+            Err(StitchError::StripTooLarge { height, .. }) if height == u64::from(u32::MAX) + 1
+        ));
+    }
+}
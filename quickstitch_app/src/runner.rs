@@ -0,0 +1,526 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{self, Component, Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    cbz,
+    error::{ExportError, StitchError},
+    job::{OnExisting, StitchJob},
+    naming::{self, PageName},
+    pipeline::{self, Page, Splitpoints, Strip},
+    source,
+};
+
+/// Pages are written here first and only moved into the output directory once
+/// every page has been exported, so a cancelled or failed run leaves nothing
+/// behind.
+const STAGING_DIRECTORY: &str = ".quickstitch-partial";
+/// Lists the files the last run wrote to the output directory, one name per
+/// line, so [`OnExisting::Clean`] only ever removes files it wrote itself.
+const MANIFEST: &str = ".quickstitch-manifest";
+/// Inside the staging directory, holds the files a run replaces until every
+/// new file is in place.
+const PREVIOUS_DIRECTORY: &str = ".previous";
+
+/// Progress reported while a job runs.
+#[derive(Debug, Clone)]
+pub enum Progress {
+    Loading { loaded: usize, total: usize },
+    FindingSplitpoints,
+    Exporting { exported: usize, total: usize },
+}
+
+/// Shared flag used to stop a running job.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The result of a finished job.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub splitpoints: Vec<usize>,
+    /// The files written to the output directory.
+    pub files: Vec<PathBuf>,
+}
+
+/// Runs a job from start to finish.
+pub fn run(
+    job: &StitchJob,
+    cancel: &CancelToken,
+    progress: &(dyn Fn(Progress) + Sync),
+) -> Result<Outcome, StitchError> {
+    let strip = load(job, cancel, progress)?;
+    if cancel.is_cancelled() {
+        return Err(StitchError::Cancelled);
+    }
+    progress(Progress::FindingSplitpoints);
+    let splitpoints = find_splitpoints(job, &strip);
+    let files = export(job, &strip, &splitpoints, cancel, progress)?;
+    Ok(Outcome {
+        splitpoints: splitpoints.cuts,
+        files,
+    })
+}
+
+/// Validates the job, then loads its images into a single strip.
+pub fn load(
+    job: &StitchJob,
+    cancel: &CancelToken,
+    progress: &(dyn Fn(Progress) + Sync),
+) -> Result<Strip, StitchError> {
+    job.validate()?;
+    let sources = source::sources(&job.input, job.sort)?;
+    pipeline::load_images(&sources, job.width, job.ignore_unloadable, cancel, progress)
+}
+
+/// Finds the rows at which the strip should be cut using the job's height
+/// limits, scan interval and sensitivity.
+pub fn find_splitpoints(job: &StitchJob, strip: &Strip) -> Splitpoints {
+    pipeline::find_splitpoints(
+        &strip.image,
+        job.max_height,
+        job.min_height,
+        job.scan_interval,
+        job.sensitivity,
+    )
+}
+
+/// Encodes the pages between the splitpoints and writes them to the output
+/// directory, or wherever the job's [`OnExisting`] policy puts them, returning
+/// the files written.
+///
+/// The splitpoints are checked first, since they may have been edited by hand.
+/// Every page is attempted even if some fail, so that all failures are
+/// reported together.
+pub fn export(
+    job: &StitchJob,
+    strip: &Strip,
+    splitpoints: &Splitpoints,
+    cancel: &CancelToken,
+    progress: &(dyn Fn(Progress) + Sync),
+) -> Result<Vec<PathBuf>, StitchError> {
+    job.validate_splitpoints(&splitpoints.cuts, strip.image.height() as usize)?;
+    let output = output_directory(job)?;
+    fs::create_dir_all(&output).map_err(|e| StitchError::CreateOutputDirectory {
+        path: output.clone(),
+        reason: e.to_string(),
+    })?;
+    let staging = output.join(STAGING_DIRECTORY);
+    fs::create_dir_all(&staging).map_err(|e| StitchError::CreateOutputDirectory {
+        path: staging.clone(),
+        reason: e.to_string(),
+    })?;
+    let exported = export_staged(job, &output, strip, splitpoints, &staging, cancel, progress);
+    // Files that couldn't be put back are still in there.
+    if !matches!(exported, Err(StitchError::Incomplete { .. })) {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    exported
+}
+
+/// Whether the directory exists and has anything in it, which is when the
+/// job's [`OnExisting`] policy comes into play.
+pub fn has_existing_output(dir: &Path) -> bool {
+    fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some())
+}
+
+/// The directory the job's pages go to once its [`OnExisting`] policy has
+/// been applied.
+fn output_directory(job: &StitchJob) -> Result<PathBuf, StitchError> {
+    if !has_existing_output(&job.output) {
+        return Ok(job.output.clone());
+    }
+    match job.on_existing {
+        OnExisting::Error => Err(StitchError::OutputExists {
+            path: job.output.clone(),
+        }),
+        OnExisting::Overwrite | OnExisting::Clean => Ok(job.output.clone()),
+        OnExisting::NewDir => {
+            // Makes `.` and the like have a name to add the suffix to.
+            let output = path::absolute(&job.output).unwrap_or_else(|_| job.output.clone());
+            let name = output
+                .file_name()
+                .map_or("stitched".into(), |name| name.to_string_lossy());
+            let mut suffix = 2;
+            loop {
+                let dir = output.with_file_name(format!("{name}-{suffix}"));
+                // A file with the name can't become the output either.
+                if !dir.exists() {
+                    return Ok(dir);
+                }
+                suffix += 1;
+            }
+        }
+    }
+}
+
+/// The names listed in a manifest. Names that aren't plain file names are
+/// left out, so a tampered manifest can't reach outside the directory.
+fn manifest_names(manifest: &str) -> impl Iterator<Item = &str> {
+    manifest.lines().filter(|name| {
+        let mut components = Path::new(name).components();
+        matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        )
+    })
+}
+
+/// Moves the staged files into the output directory and lists them in its
+/// manifest, returning the files written.
+///
+/// The files they replace, and with `clean` the files the last run wrote, are
+/// first moved aside into the staging directory. If anything fails part way
+/// through, every move is undone so the output is left as it was. `fail`
+/// builds the error for the file at the given position in `names`.
+fn commit(
+    output: &Path,
+    staging: &Path,
+    names: &[String],
+    clean: bool,
+    fail: impl Fn(usize, String) -> StitchError,
+) -> Result<Vec<PathBuf>, StitchError> {
+    let mut commit = Commit::new(output, staging)?;
+    match commit.apply(names, clean, &fail) {
+        Ok(()) => Ok(names.iter().map(|name| output.join(name)).collect()),
+        Err(e) => Err(commit.undo(e)),
+    }
+}
+
+/// The moves made by [`commit`] so far.
+struct Commit<'a> {
+    output: &'a Path,
+    staging: &'a Path,
+    /// Where the files being replaced or cleaned up wait until the new ones
+    /// are all in place.
+    previous: PathBuf,
+    /// The manifest of the last run, put back if the commit is undone.
+    manifest: Option<String>,
+    moved_aside: Vec<String>,
+    moved_in: Vec<String>,
+}
+
+impl<'a> Commit<'a> {
+    fn new(output: &'a Path, staging: &'a Path) -> Result<Self, StitchError> {
+        let previous = staging.join(PREVIOUS_DIRECTORY);
+        fs::create_dir_all(&previous).map_err(|e| StitchError::CreateOutputDirectory {
+            path: previous.clone(),
+            reason: e.to_string(),
+        })?;
+        let manifest = match fs::read_to_string(output.join(MANIFEST)) {
+            Ok(manifest) => Some(manifest),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => {
+                return Err(StitchError::CleanOutput {
+                    path: output.join(MANIFEST),
+                    reason: e.to_string(),
+                });
+            }
+        };
+        Ok(Self {
+            output,
+            staging,
+            previous,
+            manifest,
+            moved_aside: vec![],
+            moved_in: vec![],
+        })
+    }
+
+    fn apply(
+        &mut self,
+        names: &[String],
+        clean: bool,
+        fail: &impl Fn(usize, String) -> StitchError,
+    ) -> Result<(), StitchError> {
+        if clean {
+            let cleaned: Vec<String> = self
+                .manifest
+                .iter()
+                .flat_map(|manifest| manifest_names(manifest))
+                .map(str::to_string)
+                .collect();
+            for name in cleaned {
+                self.move_aside(&name)
+                    .map_err(|e| StitchError::CleanOutput {
+                        path: self.output.join(&name),
+                        reason: e.to_string(),
+                    })?;
+            }
+        }
+        for (i, name) in names.iter().enumerate() {
+            self.move_aside(name).map_err(|e| fail(i, e.to_string()))?;
+        }
+        // Written first, so the next clean finds the pages even if this run
+        // is interrupted while moving them.
+        fs::write(self.output.join(MANIFEST), names.join("\n") + "\n").map_err(|e| {
+            StitchError::Export(vec![ExportError {
+                page: None,
+                path: self.output.join(MANIFEST),
+                reason: e.to_string(),
+            }])
+        })?;
+        for (i, name) in names.iter().enumerate() {
+            fs::rename(self.staging.join(name), self.output.join(name))
+                .map_err(|e| fail(i, e.to_string()))?;
+            self.moved_in.push(name.clone());
+        }
+        Ok(())
+    }
+
+    /// Moves a file out of the output directory, if it's there.
+    fn move_aside(&mut self, name: &str) -> io::Result<()> {
+        match fs::rename(self.output.join(name), self.previous.join(name)) {
+            Ok(()) => {
+                self.moved_aside.push(name.to_string());
+                Ok(())
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Puts the output directory back as it was before the commit, returning
+    /// `error`, or if that fails too, an error listing what was left behind.
+    fn undo(self, error: StitchError) -> StitchError {
+        let mut stranded = vec![];
+        for name in &self.moved_in {
+            if fs::remove_file(self.output.join(name)).is_err() {
+                stranded.push(self.output.join(name));
+            }
+        }
+        for name in &self.moved_aside {
+            if fs::rename(self.previous.join(name), self.output.join(name)).is_err() {
+                stranded.push(self.previous.join(name));
+            }
+        }
+        let manifest = self.output.join(MANIFEST);
+        let restored = match &self.manifest {
+            Some(contents) => fs::write(&manifest, contents),
+            None => fs::remove_file(&manifest).or_else(|e| match e.kind() {
+                ErrorKind::NotFound => Ok(()),
+                _ => Err(e),
+            }),
+        };
+        if restored.is_err() {
+            stranded.push(manifest);
+        }
+        match stranded.is_empty() {
+            true => error,
+            false => StitchError::Incomplete {
+                cause: Box::new(error),
+                stranded,
+            },
+        }
+    }
+}
+
+fn export_staged(
+    job: &StitchJob,
+    output: &Path,
+    strip: &Strip,
+    splitpoints: &Splitpoints,
+    staging: &Path,
+    cancel: &CancelToken,
+    progress: &(dyn Fn(Progress) + Sync),
+) -> Result<Vec<PathBuf>, StitchError> {
+    let bounds: Vec<usize> = [0]
+        .into_iter()
+        .chain(splitpoints.cuts.iter().copied())
+        .chain([strip.image.height() as usize])
+        .collect();
+    let total = bounds.len() - 1;
+    let extension = job.format.extension();
+    let name = PageName {
+        page: 0,
+        pages: total,
+        chapter: job.input.name(),
+        first: strip
+            .inputs
+            .first()
+            .and_then(|input| input.file_stem())
+            .map_or(String::new(), |stem| stem.to_string_lossy().into_owned()),
+        date: naming::today(),
+        ext: extension.to_string(),
+    };
+    // The template was checked when the job was validated.
+    let page_name = |i: usize| {
+        PageName {
+            page: i + 1,
+            ..name.clone()
+        }
+        .render(&job.name_template)
+        .unwrap_or_else(|_| format!("page_{}.{extension}", i + 1))
+    };
+    // Pages are indexed from 0 here but numbered from 1 in errors.
+    let export_error = |index: Option<usize>, name: &str, reason: String| ExportError {
+        page: index.map(|i| i + 1),
+        path: output.join(name),
+        reason,
+    };
+
+    let exported = AtomicUsize::new(0);
+    progress(Progress::Exporting { exported: 0, total });
+    let pages: Vec<Result<Page, ExportError>> = bounds
+        .par_windows(2)
+        .enumerate()
+        .map(|(i, page)| {
+            if cancel.is_cancelled() {
+                return Err(export_error(
+                    Some(i),
+                    &page_name(i),
+                    "cancelled".to_string(),
+                ));
+            }
+            let encoded = pipeline::encode_page(
+                &strip.image,
+                page[0],
+                page[1],
+                &splitpoints.skipped,
+                &job.format,
+                job.debug,
+            )
+            .map_err(|e| export_error(Some(i), &page_name(i), e.to_string()));
+            let exported = exported.fetch_add(1, Ordering::Relaxed) + 1;
+            progress(Progress::Exporting { exported, total });
+            encoded
+        })
+        .collect();
+    if cancel.is_cancelled() {
+        return Err(StitchError::Cancelled);
+    }
+    let (pages, errors): (Vec<_>, Vec<_>) = pages.into_iter().partition(Result::is_ok);
+    if !errors.is_empty() {
+        return Err(StitchError::Export(
+            errors.into_iter().filter_map(Result::err).collect(),
+        ));
+    }
+    let pages: Vec<Page> = pages.into_iter().flatten().collect();
+
+    let names = match &job.comic_info {
+        Some(info) => {
+            let name = format!("{}.cbz", job.input.name());
+            cbz::write_cbz(&staging.join(&name), &pages, extension, info)
+                .map_err(|e| StitchError::Export(vec![export_error(None, &name, e.to_string())]))?;
+            vec![name]
+        }
+        None => {
+            let names: Vec<String> = (0..total).map(page_name).collect();
+            let errors: Vec<ExportError> = pages
+                .par_iter()
+                .zip(&names)
+                .enumerate()
+                .filter_map(|(i, (page, name))| {
+                    fs::write(staging.join(name), &page.bytes)
+                        .err()
+                        .map(|e| export_error(Some(i), name, e.to_string()))
+                })
+                .collect();
+            if !errors.is_empty() {
+                return Err(StitchError::Export(errors));
+            }
+            names
+        }
+    };
+
+    let packed = job.comic_info.is_some();
+    commit(
+        output,
+        staging,
+        &names,
+        job.on_existing == OnExisting::Clean,
+        |i, reason| {
+            let page = (!packed).then_some(i);
+            StitchError::Export(vec![export_error(page, &names[i], reason)])
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An output directory holding the pages of a last run, one of which the
+    /// new run replaces, and a file of the user's, along with a staged page.
+    fn setup() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("output");
+        let staging = output.join(STAGING_DIRECTORY);
+        fs::create_dir_all(&staging).unwrap();
+        for name in ["old_1.jpg", "page_1.jpg", "notes.txt"] {
+            fs::write(output.join(name), "old").unwrap();
+        }
+        fs::write(output.join(MANIFEST), "old_1.jpg\npage_1.jpg\n").unwrap();
+        fs::write(staging.join("page_1.jpg"), "new").unwrap();
+        (dir, output, staging)
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    fn fail(i: usize, reason: String) -> StitchError {
+        StitchError::Export(vec![ExportError {
+            page: Some(i + 1),
+            path: PathBuf::new(),
+            reason,
+        }])
+    }
+
+    #[test]
+    fn commit_replaces_pages_and_cleans_up() {
+        let (_dir, output, staging) = setup();
+        let names = vec!["page_1.jpg".to_string()];
+        let files = commit(&output, &staging, &names, true, fail).unwrap();
+        assert_eq!(files, [output.join("page_1.jpg")]);
+        assert_eq!(read(&output.join("page_1.jpg")), "new");
+        assert!(!output.join("old_1.jpg").exists());
+        assert_eq!(read(&output.join("notes.txt")), "old");
+        assert_eq!(read(&output.join(MANIFEST)), "page_1.jpg\n");
+    }
+
+    #[test]
+    fn commit_without_cleaning_keeps_other_pages() {
+        let (_dir, output, staging) = setup();
+        let names = vec!["page_1.jpg".to_string()];
+        commit(&output, &staging, &names, false, fail).unwrap();
+        assert_eq!(read(&output.join("page_1.jpg")), "new");
+        assert_eq!(read(&output.join("old_1.jpg")), "old");
+    }
+
+    #[test]
+    fn failed_commit_is_undone() {
+        let (_dir, output, staging) = setup();
+        // The second page was never staged, so moving it in fails.
+        let names = vec!["page_1.jpg".to_string(), "page_2.jpg".to_string()];
+        let error = commit(&output, &staging, &names, true, fail).unwrap_err();
+        assert!(matches!(&error, StitchError::Export(errors) if errors[0].page == Some(2)));
+        for name in ["old_1.jpg", "page_1.jpg", "notes.txt"] {
+            assert_eq!(read(&output.join(name)), "old", "{name}");
+        }
+        assert!(!output.join("page_2.jpg").exists());
+        assert_eq!(read(&output.join(MANIFEST)), "old_1.jpg\npage_1.jpg\n");
+    }
+
+    #[test]
+    fn manifests_only_name_files_in_the_directory() {
+        let manifest = "page_1.jpg\n../outside.jpg\n/absolute.jpg\nsub/page.jpg\n\n";
+        assert_eq!(manifest_names(manifest).collect::<Vec<_>>(), ["page_1.jpg"]);
+    }
+}
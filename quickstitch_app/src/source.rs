@@ -0,0 +1,256 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use image::{DynamicImage, ImageResult};
+use zip::ZipArchive;
+
+use crate::{
+    error::StitchError,
+    job::{Input, Sort},
+};
+
+const IMAGE_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+/// An image to be stitched, either on disk or already read into memory.
+pub(crate) enum Source {
+    File(PathBuf),
+    Memory { name: PathBuf, bytes: Vec<u8> },
+}
+
+impl Source {
+    /// The path of the file, or the name of the archive entry.
+    pub fn name(&self) -> &Path {
+        match self {
+            Source::File(path) => path,
+            Source::Memory { name, .. } => name,
+        }
+    }
+    pub fn decode(&self) -> ImageResult<DynamicImage> {
+        match self {
+            Source::File(path) => image::open(path),
+            Source::Memory { bytes, .. } => image::load_from_memory(bytes),
+        }
+    }
+}
+
+/// Lists the images of the input in the order they will be stitched. Archive
+/// entries are read into memory; nothing is extracted to disk.
+pub(crate) fn sources(input: &Input, sort: Sort) -> Result<Vec<Source>, StitchError> {
+    match input {
+        Input::Directory(dir) => find_images(dir, sort),
+        Input::Archive(archive) => read_archive(archive, sort),
+        Input::Images(paths) => Ok(paths.iter().cloned().map(Source::File).collect()),
+        Input::ArchiveEntries(archive, entries) => {
+            let mut images: HashMap<PathBuf, Source> = read_archive(archive, sort)?
+                .into_iter()
+                .map(|source| (source.name().to_path_buf(), source))
+                .collect();
+            entries
+                .iter()
+                .map(|entry| {
+                    images
+                        .remove(entry)
+                        .ok_or_else(|| StitchError::MissingEntry {
+                            archive: archive.clone(),
+                            entry: entry.clone(),
+                        })
+                })
+                .collect()
+        }
+    }
+}
+
+fn find_images(dir: &Path, sort: Sort) -> Result<Vec<Source>, StitchError> {
+    let entries = fs::read_dir(dir).map_err(|e| StitchError::ReadDirectory {
+        path: dir.to_path_buf(),
+        reason: e.to_string(),
+    })?;
+    let mut images: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && is_image(path))
+        .collect();
+    sort_by_name(&mut images, sort);
+    Ok(images.into_iter().map(Source::File).collect())
+}
+
+fn read_archive(archive: &Path, sort: Sort) -> Result<Vec<Source>, StitchError> {
+    let read_error = |e: &dyn ToString| StitchError::ReadArchive {
+        path: archive.to_path_buf(),
+        reason: e.to_string(),
+    };
+    let file = File::open(archive).map_err(|e| read_error(&e))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| read_error(&e))?;
+    let mut images = vec![];
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).map_err(|e| read_error(&e))?;
+        let name = PathBuf::from(entry.name());
+        let hidden = name.components().any(|component| {
+            let component = component.as_os_str().to_string_lossy();
+            component.starts_with('.') || component == "__MACOSX"
+        });
+        if entry.is_dir() || hidden || !is_image(&name) {
+            continue;
+        }
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut bytes).map_err(|e| read_error(&e))?;
+        images.push(Source::Memory { name, bytes });
+    }
+    images.sort_by(|a, b| compare_names(a.name(), b.name(), sort));
+    Ok(images)
+}
+
+/// Finds every chapter in the given directory, sorted by name.
+///
+/// A chapter is either a child directory or a `.cbz`/`.zip` archive.
+pub fn find_chapters(parent: &Path, sort: Sort) -> Result<Vec<PathBuf>, StitchError> {
+    let entries = fs::read_dir(parent).map_err(|e| StitchError::ReadDirectory {
+        path: parent.to_path_buf(),
+        reason: e.to_string(),
+    })?;
+    let mut chapters: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir() || is_archive(path))
+        .collect();
+    sort_by_name(&mut chapters, sort);
+    Ok(chapters)
+}
+
+/// Whether the path is a `.cbz`/`.zip` file.
+pub fn is_archive(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ["cbz", "zip"].contains(&ext.to_lowercase().as_str()))
+}
+
+/// Whether the path has the extension of a supported image format.
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Sorts paths by file name, the same way inputs are sorted when stitching.
+pub fn sort_by_name(paths: &mut [PathBuf], sort: Sort) {
+    paths.sort_by(|a, b| compare_names(a, b, sort));
+}
+
+fn compare_names(a: &Path, b: &Path, sort: Sort) -> Ordering {
+    let a = a.file_name().unwrap_or_default().to_string_lossy();
+    let b = b.file_name().unwrap_or_default().to_string_lossy();
+    match sort {
+        Sort::Natural => natord::compare(&a, &b),
+        Sort::Logical => a.cmp(&b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+
+    fn names(sources: &[Source]) -> Vec<&Path> {
+        sources.iter().map(Source::name).collect()
+    }
+
+    fn write_zip(path: &Path, entries: &[&str]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for entry in entries {
+            match entry.strip_suffix('/') {
+                Some(dir) => zip.add_directory(dir, SimpleFileOptions::default()),
+                None => zip
+                    .start_file(*entry, SimpleFileOptions::default())
+                    .and_then(|()| Ok(zip.write_all(entry.as_bytes())?)),
+            }
+            .unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn archives_skip_hidden_entries_and_non_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("chapter.cbz");
+        write_zip(
+            &archive,
+            &[
+                "10.png",
+                "pages/",
+                "pages/9.jpg",
+                "__MACOSX/pages/._9.jpg",
+                ".thumbnail.png",
+                "pages/.hidden/8.png",
+                "ComicInfo.xml",
+            ],
+        );
+        let sources = sources(&Input::Archive(archive), Sort::Natural).unwrap();
+        assert_eq!(
+            names(&sources),
+            [Path::new("pages/9.jpg"), Path::new("10.png")]
+        );
+        let Source::Memory { bytes, .. } = &sources[0] else {
+            panic!("archive entries are read into memory");
+        };
+        assert_eq!(bytes, b"pages/9.jpg");
+    }
+
+    #[test]
+    fn archive_entries_keep_the_given_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("chapter.zip");
+        write_zip(&archive, &["1.png", "2.png"]);
+        let entries = vec![PathBuf::from("2.png"), PathBuf::from("1.png")];
+        let input = Input::ArchiveEntries(archive.clone(), entries);
+        let listed = sources(&input, Sort::Natural).unwrap();
+        assert_eq!(names(&listed), [Path::new("2.png"), Path::new("1.png")]);
+
+        let missing = Input::ArchiveEntries(archive, vec![PathBuf::from("3.png")]);
+        assert!(matches!(
+            sources(&missing, Sort::Natural),
+            Err(StitchError::MissingEntry { entry, .. }) if entry == Path::new("3.png")
+        ));
+    }
+
+    #[test]
+    fn directories_list_images_sorted_by_name() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["10.PNG", "9.jpeg", "8.webp", "notes.txt", "cover.gif"] {
+            fs::write(dir.path().join(name), []).unwrap();
+        }
+        fs::create_dir(dir.path().join("7.png")).unwrap();
+        let input = Input::Directory(dir.path().to_path_buf());
+        let file_names = |sort| {
+            sources(&input, sort)
+                .unwrap()
+                .iter()
+                .map(|source| source.name().file_name().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(file_names(Sort::Natural), ["8.webp", "9.jpeg", "10.PNG"]);
+        assert_eq!(file_names(Sort::Logical), ["10.PNG", "8.webp", "9.jpeg"]);
+    }
+
+    #[test]
+    fn chapters_are_directories_and_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("ch10")).unwrap();
+        fs::create_dir(dir.path().join("ch2")).unwrap();
+        fs::write(dir.path().join("ch3.CBZ"), []).unwrap();
+        fs::write(dir.path().join("notes.txt"), []).unwrap();
+        let chapters = find_chapters(dir.path(), Sort::Natural).unwrap();
+        let chapters: Vec<_> = chapters
+            .iter()
+            .map(|path| path.file_name().unwrap())
+            .collect();
+        assert_eq!(chapters, ["ch2", "ch3.CBZ", "ch10"]);
+    }
+}
@@ -0,0 +1,476 @@
+use std::{
+    collections::BTreeMap,
+    env, fmt, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail, ensure};
+use clap::{ArgMatches, ValueEnum, parser::ValueSource};
+use serde::Deserialize;
+
+use crate::Cli;
+
+/// The config file read from the working directory. It takes precedence over
+/// the one in the user's config directory.
+const LOCAL_CONFIG: &str = "quickstitch.toml";
+
+/// The flags whose settings can also come from the environment, a preset or a
+/// config file.
+const SETTINGS: [&str; 12] = [
+    "output",
+    "on_existing",
+    "sort",
+    "max_height",
+    "min_height",
+    "scan_interval",
+    "sensitivity",
+    "format",
+    "quality",
+    "width",
+    "name_template",
+    "debug",
+];
+
+/// Settings that can be given in a config file, preset or environment
+/// variable, named after their flags.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Settings {
+    pub output: Option<PathBuf>,
+    pub on_existing: Option<crate::OnExisting>,
+    pub sort: Option<crate::Sort>,
+    pub max_height: Option<usize>,
+    pub min_height: Option<usize>,
+    pub scan_interval: Option<usize>,
+    pub sensitivity: Option<u8>,
+    pub format: Option<crate::ImageFormat>,
+    pub quality: Option<u8>,
+    pub width: Option<u32>,
+    pub name_template: Option<String>,
+    pub debug: Option<bool>,
+}
+
+impl Settings {
+    /// Reads the settings given through environment variables, looked up by
+    /// name with `env`.
+    fn from_env(env: &dyn Fn(&str) -> Option<String>) -> Result<Self> {
+        let mut table = toml::Table::new();
+        for id in SETTINGS {
+            let Some(value) = env(&env_var(id)) else {
+                continue;
+            };
+            // Typed like the flags, so that the usual parsing errors apply.
+            let value = match id {
+                "debug" => toml::Value::Boolean(!matches!(
+                    value.to_lowercase().as_str(),
+                    "" | "0" | "n" | "no" | "f" | "false" | "off"
+                )),
+                "max_height" | "min_height" | "scan_interval" | "sensitivity" | "quality"
+                | "width" => value
+                    .trim()
+                    .parse()
+                    .map_or(toml::Value::String(value), toml::Value::Integer),
+                _ => toml::Value::String(value),
+            };
+            table.insert(id.replace('_', "-"), value);
+        }
+        let settings: Settings = toml::Value::Table(table)
+            .try_into()
+            .context("Unable to parse the QUICKSTITCH_* environment variables")?;
+        settings.check("environment variable QUICKSTITCH_QUALITY")?;
+        Ok(settings)
+    }
+
+    /// Checks the values serde can't, naming `source` on failure.
+    fn check(&self, source: &str) -> Result<()> {
+        if let Some(quality) = self.quality {
+            ensure!(
+                (1..=100).contains(&quality),
+                "{source}: quality must be between 1 and 100"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A config file: top-level settings followed by `[preset.<name>]` sections.
+struct ConfigFile {
+    path: PathBuf,
+    settings: Settings,
+    presets: BTreeMap<String, Settings>,
+}
+
+impl ConfigFile {
+    /// Reads a config file, or returns `None` if it doesn't exist.
+    fn read(path: &Path) -> Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Unable to read config {}", path.display()));
+            }
+        };
+        let parse_error = || format!("Unable to parse config {}", path.display());
+        let mut table: toml::Table = toml::from_str(&contents).with_context(parse_error)?;
+        let presets: BTreeMap<String, Settings> = match table.remove("preset") {
+            Some(presets) => presets.try_into().with_context(parse_error)?,
+            None => BTreeMap::new(),
+        };
+        let settings: Settings = toml::Value::Table(table)
+            .try_into()
+            .with_context(parse_error)?;
+        for settings in presets.values().chain([&settings]) {
+            settings.check(&path.display().to_string())?;
+        }
+        Ok(Some(Self {
+            path: path.to_path_buf(),
+            settings,
+            presets,
+        }))
+    }
+}
+
+/// The config files to read, most important first.
+fn config_paths() -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from(LOCAL_CONFIG)];
+    if let Some(dir) = dirs::config_dir() {
+        paths.push(dir.join("quickstitch").join("config.toml"));
+    }
+    paths
+}
+
+/// Where the final value of a setting came from.
+#[derive(Debug)]
+pub enum Origin {
+    Cli,
+    Env(String),
+    Preset(String, PathBuf),
+    File(PathBuf),
+    Default,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Cli => write!(f, "command line"),
+            Origin::Env(var) => write!(f, "environment variable {var}"),
+            Origin::Preset(name, path) => write!(f, "preset \"{name}\" in {}", path.display()),
+            Origin::File(path) => write!(f, "{}", path.display()),
+            Origin::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// Fills in every setting that wasn't given on the command line, first from
+/// the environment, then from the chosen preset, then from the config files.
+///
+/// Returns where each setting's final value came from, keyed by flag name.
+pub fn apply(cli: &mut Cli, matches: &ArgMatches) -> Result<Vec<(&'static str, Origin)>> {
+    let files = config_paths()
+        .iter()
+        .filter_map(|path| ConfigFile::read(path).transpose())
+        .collect::<Result<Vec<_>>>()?;
+    apply_layers(cli, matches, &|var| env::var(var).ok(), &files)
+}
+
+/// Like [`apply`], with environment variables looked up with `env` and the
+/// config files already read, most important first.
+fn apply_layers(
+    cli: &mut Cli,
+    matches: &ArgMatches,
+    env: &dyn Fn(&str) -> Option<String>,
+    files: &[ConfigFile],
+) -> Result<Vec<(&'static str, Origin)>> {
+    let env_settings = Settings::from_env(env)?;
+    let mut layers = vec![(Origin::Env(String::new()), &env_settings)];
+    if cli.preset.is_none() {
+        cli.preset = env(&env_var("preset"));
+    }
+    if let Some(name) = &cli.preset {
+        for file in files {
+            if let Some(preset) = file.presets.get(name) {
+                layers.push((Origin::Preset(name.clone(), file.path.clone()), preset));
+            }
+        }
+        if layers.len() == 1 {
+            bail!("Unknown preset \"{name}\"");
+        }
+    }
+    for file in files {
+        layers.push((Origin::File(file.path.clone()), &file.settings));
+    }
+
+    let l = &layers;
+    Ok(vec![
+        resolve(matches, "output", &mut cli.output, l, |s| s.output.clone()),
+        resolve(matches, "on_existing", &mut cli.on_existing, l, |s| {
+            s.on_existing.clone()
+        }),
+        resolve(matches, "sort", &mut cli.sort, l, |s| s.sort.clone()),
+        resolve(matches, "max_height", &mut cli.max_height, l, |s| {
+            s.max_height
+        }),
+        resolve(matches, "min_height", &mut cli.min_height, l, |s| {
+            s.min_height
+        }),
+        resolve(matches, "scan_interval", &mut cli.scan_interval, l, |s| {
+            s.scan_interval
+        }),
+        resolve(matches, "sensitivity", &mut cli.sensitivity, l, |s| {
+            s.sensitivity
+        }),
+        resolve(matches, "format", &mut cli.format, l, |s| s.format.clone()),
+        resolve(matches, "quality", &mut cli.quality, l, |s| s.quality),
+        resolve(matches, "width", &mut cli.width, l, |s| s.width.map(Some)),
+        resolve(matches, "name_template", &mut cli.name_template, l, |s| {
+            s.name_template.clone()
+        }),
+        resolve(matches, "debug", &mut cli.debug, l, |s| s.debug),
+    ])
+}
+
+fn resolve<T>(
+    matches: &ArgMatches,
+    id: &'static str,
+    value: &mut T,
+    layers: &[(Origin, &Settings)],
+    get: impl Fn(&Settings) -> Option<T>,
+) -> (&'static str, Origin) {
+    let origin = match matches.value_source(id) {
+        Some(ValueSource::CommandLine) => Origin::Cli,
+        _ => layers
+            .iter()
+            .find_map(|(origin, settings)| {
+                get(settings).map(|found| {
+                    *value = found;
+                    match origin {
+                        Origin::Env(_) => Origin::Env(env_var(id)),
+                        Origin::Preset(name, path) => Origin::Preset(name.clone(), path.clone()),
+                        Origin::File(path) => Origin::File(path.clone()),
+                        _ => {
+                            unreachable!("layers only come from the environment, presets and files")
+                        }
+                    }
+                })
+            })
+            .unwrap_or(Origin::Default),
+    };
+    (id, origin)
+}
+
+/// The environment variable a setting can be given through.
+fn env_var(id: &str) -> String {
+    format!("QUICKSTITCH_{}", id.to_uppercase())
+}
+
+/// Prints the final settings as a config file, noting where each came from.
+pub fn print(cli: &Cli, origins: &[(&str, Origin)]) {
+    for (id, origin) in origins {
+        let value = match *id {
+            "output" => Some(toml::Value::from(cli.output.display().to_string()).to_string()),
+            "on_existing" => Some(name(&cli.on_existing)),
+            "sort" => Some(name(&cli.sort)),
+            "max_height" => Some(cli.max_height.to_string()),
+            "min_height" => Some(cli.min_height.to_string()),
+            "scan_interval" => Some(cli.scan_interval.to_string()),
+            "sensitivity" => Some(cli.sensitivity.to_string()),
+            "format" => Some(name(&cli.format)),
+            "quality" => Some(cli.quality.to_string()),
+            "width" => cli.width.map(|width| width.to_string()),
+            "name_template" => Some(toml::Value::from(cli.name_template.clone()).to_string()),
+            "debug" => Some(cli.debug.to_string()),
+            _ => unreachable!("every resolved setting is printed"),
+        };
+        let key = id.replace('_', "-");
+        match value {
+            Some(value) => println!("{key} = {value}  # {origin}"),
+            None => println!("# {key} is unset  # {origin}"),
+        }
+    }
+}
+
+fn name(value: &impl ValueEnum) -> String {
+    let name = value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default();
+    format!("\"{name}\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, FromArgMatches};
+
+    use super::*;
+
+    /// Parses the arguments and applies the environment variables, given as
+    /// name and value pairs, and the config files, given as their contents,
+    /// most important first.
+    fn resolve_in(
+        args: &[&str],
+        env: &[(&str, &str)],
+        files: &[&str],
+    ) -> Result<(Cli, Vec<(&'static str, Origin)>)> {
+        let dir = tempfile::tempdir().unwrap();
+        let files = files
+            .iter()
+            .enumerate()
+            .map(|(i, contents)| {
+                let path = dir.path().join(format!("{i}.toml"));
+                fs::write(&path, contents).unwrap();
+                Ok(ConfigFile::read(&path)?.unwrap())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let matches = Cli::command()
+            .try_get_matches_from(["quickstitch", "1.png"].iter().chain(args))
+            .unwrap();
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        let env = |var: &str| {
+            env.iter()
+                .find(|(name, _)| *name == var)
+                .map(|(_, value)| value.to_string())
+        };
+        let origins = apply_layers(&mut cli, &matches, &env, &files)?;
+        Ok((cli, origins))
+    }
+
+    fn resolve_with(args: &[&str], files: &[&str]) -> Result<(Cli, Vec<(&'static str, Origin)>)> {
+        resolve_in(args, &[], files)
+    }
+
+    /// Where the setting came from, with the temporary directory left out.
+    fn origin(origins: &[(&str, Origin)], id: &str) -> String {
+        let (_, origin) = origins.iter().find(|(found, _)| *found == id).unwrap();
+        match origin {
+            Origin::Preset(name, path) => {
+                format!("preset {name} in {}", path.file_name().unwrap().display())
+            }
+            Origin::File(path) => path.file_name().unwrap().display().to_string(),
+            origin => origin.to_string(),
+        }
+    }
+
+    const LOCAL: &str = "
+        max-height = 1000
+        min-height = 100
+        quality = 90
+
+        [preset.small]
+        max-height = 2000
+    ";
+    const USER: &str = "
+        max-height = 3000
+        min-height = 300
+        quality = 80
+        scan-interval = 7
+
+        [preset.small]
+        max-height = 4000
+        min-height = 400
+    ";
+
+    #[test]
+    fn command_line_beats_everything() {
+        let (cli, origins) = resolve_with(
+            &["--preset", "small", "--max-height", "500"],
+            &[LOCAL, USER],
+        )
+        .unwrap();
+        assert_eq!(cli.max_height, 500);
+        assert_eq!(origin(&origins, "max_height"), "command line");
+    }
+
+    #[test]
+    fn environment_beats_presets_and_files() {
+        let env = [
+            ("QUICKSTITCH_SENSITIVITY", "42"),
+            ("QUICKSTITCH_DEBUG", "1"),
+        ];
+        let (cli, origins) =
+            resolve_in(&["--preset", "small"], &env, &["sensitivity = 10", USER]).unwrap();
+        assert_eq!(cli.sensitivity, 42);
+        assert_eq!(
+            origin(&origins, "sensitivity"),
+            "environment variable QUICKSTITCH_SENSITIVITY"
+        );
+        assert!(cli.debug);
+        // The command line still comes first.
+        let (cli, _) = resolve_in(&["--sensitivity", "7"], &env, &[]).unwrap();
+        assert_eq!(cli.sensitivity, 7);
+    }
+
+    #[test]
+    fn environment_can_choose_the_preset() {
+        let env = [("QUICKSTITCH_PRESET", "small")];
+        let (cli, origins) = resolve_in(&[], &env, &[LOCAL]).unwrap();
+        assert_eq!(cli.max_height, 2000);
+        assert_eq!(origin(&origins, "max_height"), "preset small in 0.toml");
+    }
+
+    #[test]
+    fn environment_values_are_checked() {
+        for (var, value) in [
+            ("QUICKSTITCH_SENSITIVITY", "256"),
+            ("QUICKSTITCH_MAX_HEIGHT", "tall"),
+            ("QUICKSTITCH_FORMAT", "gif"),
+            ("QUICKSTITCH_QUALITY", "0"),
+        ] {
+            assert!(
+                resolve_in(&[], &[(var, value)], &[]).is_err(),
+                "{var}={value}"
+            );
+        }
+    }
+
+    #[test]
+    fn presets_beat_top_level_settings_local_first() {
+        let (cli, origins) = resolve_with(&["--preset", "small"], &[LOCAL, USER]).unwrap();
+        assert_eq!(cli.max_height, 2000);
+        assert_eq!(origin(&origins, "max_height"), "preset small in 0.toml");
+        // The user's preset fills in what the local one leaves out.
+        assert_eq!(cli.min_height, 400);
+        assert_eq!(origin(&origins, "min_height"), "preset small in 1.toml");
+    }
+
+    #[test]
+    fn local_top_level_beats_user_top_level() {
+        let (cli, origins) = resolve_with(&[], &[LOCAL, USER]).unwrap();
+        assert_eq!(cli.quality, 90);
+        assert_eq!(origin(&origins, "quality"), "0.toml");
+        assert_eq!(cli.scan_interval, 7);
+        assert_eq!(origin(&origins, "scan_interval"), "1.toml");
+    }
+
+    #[test]
+    fn defaults_fill_in_the_rest() {
+        let (cli, origins) = resolve_with(&[], &[LOCAL, USER]).unwrap();
+        assert!(!cli.debug);
+        assert_eq!(origin(&origins, "debug"), "default");
+        assert_eq!(origin(&origins, "width"), "default");
+    }
+
+    #[test]
+    fn unknown_presets_are_an_error() {
+        let error = resolve_with(&["--preset", "large"], &[LOCAL]).unwrap_err();
+        assert_eq!(error.to_string(), "Unknown preset \"large\"");
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        for contents in ["max-hieght = 10", "[preset.small]\nmax-hieght = 10"] {
+            let error = format!("{:#}", resolve_with(&[], &[contents]).unwrap_err());
+            assert!(error.contains("unknown field `max-hieght`"), "{error}");
+        }
+    }
+
+    #[test]
+    fn quality_is_range_checked() {
+        let error = resolve_with(&[], &["quality = 0"]).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .ends_with("quality must be between 1 and 100")
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use quickstitch_app::Strip;
+
+/// What `--dry-run` reports for a chapter.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub width: u32,
+    pub height: usize,
+    pub pages: Vec<PageReport>,
+}
+
+/// The rows of the strip an output page covers, and the inputs they came from.
+#[derive(Debug, Serialize)]
+pub struct PageReport {
+    pub page: usize,
+    /// The first row of the strip in the page.
+    pub start: usize,
+    /// The row of the strip just past the end of the page.
+    pub end: usize,
+    pub height: usize,
+    /// The input and row the page starts at.
+    pub from: SourceRow,
+    /// The input and row the page ends at, inclusive.
+    pub to: SourceRow,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceRow {
+    pub input: PathBuf,
+    pub row: usize,
+}
+
+impl SourceRow {
+    pub fn new((input, row): (&Path, usize)) -> Self {
+        Self {
+            input: input.to_path_buf(),
+            row,
+        }
+    }
+}
+
+impl Report {
+    pub fn new(strip: &Strip, splitpoints: &[usize]) -> Self {
+        let height = strip.image.height() as usize;
+        let bounds: Vec<usize> = [0]
+            .into_iter()
+            .chain(splitpoints.iter().copied())
+            .chain([height])
+            .collect();
+        let pages = bounds
+            .windows(2)
+            .enumerate()
+            .map(|(i, bounds)| PageReport {
+                page: i + 1,
+                start: bounds[0],
+                end: bounds[1],
+                height: bounds[1] - bounds[0],
+                from: SourceRow::new(strip.locate(bounds[0])),
+                to: SourceRow::new(strip.locate(bounds[1] - 1)),
+            })
+            .collect();
+        Self {
+            width: strip.image.width(),
+            height,
+            pages,
+        }
+    }
+
+    /// Prints the report as a table, one row per page.
+    pub fn print_table(&self) {
+        print!("{}", self.table());
+    }
+
+    /// The table [`Report::print_table`] prints, followed by a summary line.
+    fn table(&self) -> String {
+        let name = |row: &SourceRow| {
+            row.input
+                .file_name()
+                .unwrap_or(row.input.as_os_str())
+                .to_string_lossy()
+                .into_owned()
+        };
+        let rows: Vec<[String; 4]> = self
+            .pages
+            .iter()
+            .map(|page| {
+                [
+                    page.page.to_string(),
+                    page.height.to_string(),
+                    format!("{}..{}", page.start, page.end),
+                    format!(
+                        "{}:{} - {}:{}",
+                        name(&page.from),
+                        page.from.row,
+                        name(&page.to),
+                        page.to.row
+                    ),
+                ]
+            })
+            .collect();
+        let header = ["Page", "Height", "Rows", "Source range"];
+        let widths: Vec<usize> = (0..header.len())
+            .map(|column| {
+                rows.iter()
+                    .map(|row| row[column].len())
+                    .fold(header[column].len(), usize::max)
+            })
+            .collect();
+        let mut table = String::new();
+        for row in [header.map(String::from)].iter().chain(&rows) {
+            let _ = writeln!(
+                table,
+                "{:>w0$}  {:>w1$}  {:<w2$}  {}",
+                row[0],
+                row[1],
+                row[2],
+                row[3],
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2],
+            );
+        }
+        let _ = writeln!(
+            table,
+            "{} pages from a {}x{} strip",
+            self.pages.len(),
+            self.width,
+            self.height
+        );
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cli, stitch_job};
+    use clap::Parser;
+    use image::RgbImage;
+    use quickstitch_app::{CancelToken, Input};
+
+    /// Loads a 30 and a 50 pixel tall image into a single 80 pixel strip.
+    fn strip(dir: &Path) -> Strip {
+        let images: Vec<PathBuf> = [("1.png", 30), ("2.png", 50)]
+            .into_iter()
+            .map(|(name, height)| {
+                let path = dir.join(name);
+                RgbImage::new(20, height).save(&path).unwrap();
+                path
+            })
+            .collect();
+        let cli = Cli::try_parse_from(["quickstitch"]).unwrap();
+        let job = stitch_job(&cli, Input::Images(images), &dir.join("stitched"));
+        quickstitch_app::load(&job, &CancelToken::default(), &|_| {}).unwrap()
+    }
+
+    #[test]
+    fn pages_are_traced_back_to_their_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = Report::new(&strip(dir.path()), &[40]);
+        assert_eq!((report.width, report.height), (20, 80));
+        let pages: Vec<_> = report
+            .pages
+            .iter()
+            .map(|page| {
+                (
+                    page.page,
+                    page.start,
+                    page.end,
+                    page.height,
+                    page.from.input.strip_prefix(dir.path()).unwrap(),
+                    page.from.row,
+                    page.to.input.strip_prefix(dir.path()).unwrap(),
+                    page.to.row,
+                )
+            })
+            .collect();
+        assert_eq!(
+            pages,
+            [
+                (1, 0, 40, 40, Path::new("1.png"), 0, Path::new("2.png"), 9),
+                (
+                    2,
+                    40,
+                    80,
+                    40,
+                    Path::new("2.png"),
+                    10,
+                    Path::new("2.png"),
+                    49
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn table_lists_every_page_then_a_summary() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = Report::new(&strip(dir.path()), &[40]);
+        assert_eq!(
+            report.table(),
+            "\
+Page  Height  Rows    Source range
+   1      40  0..40   1.png:0 - 2.png:9
+   2      40  40..80  2.png:10 - 2.png:49
+2 pages from a 20x80 strip
+"
+        );
+    }
+
+    #[test]
+    fn a_strip_without_splitpoints_is_one_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = Report::new(&strip(dir.path()), &[]);
+        assert_eq!(
+            report.table(),
+            "\
+Page  Height  Rows   Source range
+   1      80  0..80  1.png:0 - 2.png:49
+1 pages from a 20x80 strip
+"
+        );
+    }
+}
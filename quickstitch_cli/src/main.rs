@@ -1,33 +1,122 @@
 pub mod _cli;
+mod config;
+mod inspect;
+mod plan;
+mod report;
 
-use clap::{value_parser, Args, Parser, ValueEnum};
-use log::{error, info};
-use quickstitch as qs;
-use quickstitch::Stitcher;
+use clap::error::ErrorKind;
+use clap::{value_parser, Args, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use config::Origin;
+use inspect::Report;
+use log::{error, info, warn};
+use notify_debouncer_full::{
+    new_debouncer,
+    notify::{EventKind, RecursiveMode},
+};
+use plan::Plan;
+use quickstitch_app::{self as qs, CancelToken, ComicInfo, Setting, StitchError, StitchJob};
+use report::{ChapterReport, ErrorReport, OutputKind, RunReport};
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::process::exit;
-use std::time::Instant;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone, ValueEnum)]
+/// How long the input directory has to stay unchanged before re-stitching in
+/// `--watch` mode.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Every chapter of a `--batch` failed. A single chapter that fails exits with
+/// the code of its failure instead.
+const TOTAL_FAILURE: i32 = 1;
+/// Every page was written, but some inputs couldn't be loaded and were left
+/// out.
+const SKIPPED_INPUTS: i32 = 3;
+/// Some, but not all, chapters of a `--batch` failed.
+const PARTIAL_BATCH: i32 = 4;
+/// The output directory couldn't be used, as opposed to a page failing to
+/// export into it.
+const OUTPUT_UNUSABLE: i32 = 5;
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0   Every page was written
+  3   Every page was written, but some inputs couldn't be loaded and were left out
+  5   The output directory couldn't be created or cleaned, or already has files
+      in it with --on-existing=error
+  64  Invalid arguments or settings
+  65  An input couldn't be loaded with --strict, the inputs are too tall to stitch
+      together, or a plan doesn't match its inputs
+  66  No inputs were found or none of them could be loaded
+  73  Some pages couldn't be exported, so none were written
+  74  Reading or writing failed for another reason
+  78  A config file couldn't be read
+A --batch whose chapters fail exits with 1 if every chapter failed and with 4
+if only some did, rather than with the code of each chapter's failure.";
+
+#[derive(Debug, Clone, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 enum ImageFormat {
     Png,
     Webp,
     Jpg,
     Jpeg,
 }
+#[derive(Debug, Clone, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum OnExisting {
+    /// Stop without writing anything.
+    Error,
+    /// Replace pages with the same names, leaving any other files.
+    Overwrite,
+    /// First remove the files the last run wrote to the directory.
+    Clean,
+    /// Write to `<OUTPUT>-2`, `<OUTPUT>-3`, ... instead.
+    NewDir,
+}
+#[derive(Debug, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Archive {
+    Cbz,
+}
 #[derive(Debug, Clone, ValueEnum)]
+enum DryRun {
+    Table,
+    Json,
+}
+#[derive(Debug, Clone, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 enum Sort {
     Natural,
     Logical,
 }
 #[derive(Debug, Clone, Args)]
-#[group(required = true, multiple = false)]
+#[group(multiple = false)]
 struct Input {
     /// The images to stitch.
     images: Option<Vec<PathBuf>>,
     /// A directory of images to stitch.
     #[clap(long, short, alias = "dir")]
     dir: Option<PathBuf>,
+    /// A `.cbz` or `.zip` archive of images to stitch.
+    ///
+    /// The images are read straight out of the archive and sorted with
+    /// `--sort`.
+    #[clap(long, visible_alias = "cbz")]
+    zip: Option<PathBuf>,
+    /// A plan written by `--plan-out` to export as-is.
+    ///
+    /// The plan's inputs, width and splitpoints are used instead of finding
+    /// new splitpoints, so hand-edited cuts are kept exactly.
+    #[clap(long)]
+    plan_in: Option<PathBuf>,
+    /// A directory of chapters to stitch one after another.
+    ///
+    /// Every child directory and `.cbz`/`.zip` archive is treated as its own
+    /// chapter and stitched into a matching subdirectory of `--output`. Failed
+    /// chapters are reported at the end without stopping the others.
+    #[clap(long)]
+    batch: Option<PathBuf>,
 }
 
 /// Quickly stitch raws.
@@ -35,7 +124,7 @@ struct Input {
 /// A list of images can provided as input, or the `--dir` flag can be used
 /// instead to specify a directory of images to stitch.
 #[derive(Debug, Clone, Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, after_help = EXIT_CODES)]
 struct Cli {
     #[clap(flatten)]
     input: Input,
@@ -44,7 +133,19 @@ struct Cli {
     #[clap(long, short, default_value = "./stitched")]
     output: PathBuf,
 
-    /// The sorting method used to sort the images before stitching (only works with `--dir`).
+    /// What to do when the output directory already has files in it.
+    ///
+    /// A list of the files written is kept in the output directory, so `clean`
+    /// never removes anything it didn't write. `--watch` only works with
+    /// `overwrite` and `clean`, which every rebuild uses too.
+    #[clap(
+        long,
+        default_value_t = OnExisting::Overwrite
+    )]
+    #[arg(value_enum)]
+    on_existing: OnExisting,
+
+    /// The sorting method used to sort the images before stitching (only works with `--dir` and `--zip`).
     ///
     /// Given the images ["9.jpeg", "10.jpeg", "8.jpeg", "11.jpeg"]:
     ///   - Logical: ["10.jpeg", "11.jpeg", 8.jpeg", "9.jpeg"]
@@ -57,17 +158,27 @@ struct Cli {
     ///
     /// Stitched images will aim to be as tall as this parameter,
     /// but they may be shorter if visual elements are in the way.
-    #[clap(long, visible_alias = "max", default_value_t = 5000)]
+    /// Must not be greater than 65535 for JPEG or 16383 for WebP, the largest
+    /// images those formats can hold.
+    #[clap(
+        long,
+        visible_alias = "max",
+        default_value_t = qs::DEFAULT_MAX_HEIGHT
+    )]
     max_height: usize,
 
     /// The minimum height for stitched images.
-    #[clap(long, visible_alias = "min", default_value_t = 0)]
+    #[clap(
+        long,
+        visible_alias = "min",
+        default_value_t = qs::DEFAULT_MIN_HEIGHT
+    )]
     min_height: usize,
 
     /// The interval at which lines of pixels are scanned. For example,
     /// a value of 5 means every 5th horizontal line of pixels will be
     /// analyzed.
-    #[clap(long, default_value_t = 5)]
+    #[clap(long, default_value_t = qs::DEFAULT_SCAN_INTERVAL)]
     scan_interval: usize,
 
     /// The threshold value between 0 and 255 for determining when a line of
@@ -75,7 +186,7 @@ struct Cli {
     /// to be used as a splitpoint regardless of the line's pixels' values,
     /// while 255 would only allow the line to be used as a splitpoint if
     /// all the pixels in the line have the same value.
-    #[clap(long, short, default_value_t = 220)]
+    #[clap(long, short, default_value_t = qs::DEFAULT_SENSITIVITY)]
     #[arg(value_parser(value_parser!(u8).range(0..=255)))]
     sensitivity: u8,
 
@@ -99,6 +210,21 @@ struct Cli {
     #[clap(long, short)]
     width: Option<u32>,
 
+    /// Fail instead of leaving out inputs that can't be loaded.
+    #[clap(long, env = "QUICKSTITCH_STRICT")]
+    strict: bool,
+
+    /// How to name the exported pages.
+    ///
+    /// `{page}` is the page number and `{pages}` the number of pages, which
+    /// can be zero-padded like `{page:03}`. `{chapter}` is the name of the
+    /// input directory or archive, `{first}` the file name of the first input
+    /// image without its extension, `{date}` today's date as YYYY-MM-DD (UTC)
+    /// and `{ext}` the extension of `--format`. Pages packed with `--archive`
+    /// keep their own names.
+    #[clap(long, default_value = qs::DEFAULT_NAME_TEMPLATE)]
+    name_template: String,
+
     /// Enable debug mode.
     ///
     /// Using the stitcher in debug mode will result in red and light blue lines
@@ -106,74 +232,880 @@ struct Cli {
     /// points and light blue lines denoting potential cut points that were skipped.
     #[clap(long, default_value_t = false)]
     debug: bool,
+
+    /// Pack the stitched images into a single archive instead of loose files.
+    ///
+    /// The archive is placed in the output directory, named after the input
+    /// directory or archive, and includes a generated `ComicInfo.xml`.
+    #[clap(long)]
+    #[arg(value_enum)]
+    archive: Option<Archive>,
+
+    /// The series name to write to `ComicInfo.xml`.
+    #[clap(long, requires = "archive")]
+    series: Option<String>,
+
+    /// The chapter number to write to `ComicInfo.xml`.
+    #[clap(long, requires = "archive")]
+    chapter: Option<String>,
+
+    /// The chapter title to write to `ComicInfo.xml`.
+    #[clap(long, requires = "archive")]
+    title: Option<String>,
+
+    /// Write the ordered inputs, width and splitpoints to this file.
+    ///
+    /// The plan can be edited by hand and exported with `--plan-in`. Inputs
+    /// are stored relative to the plan, so it can be used from any directory.
+    #[clap(long, conflicts_with = "batch")]
+    plan_out: Option<PathBuf>,
+
+    /// Keep running and re-stitch whenever images in `--dir` are added,
+    /// changed or removed.
+    #[clap(long, requires = "dir")]
+    watch: bool,
+
+    /// Find splitpoints and report the resulting pages without exporting
+    /// anything.
+    ///
+    /// Each page's height and the rows of the inputs it is made of are printed
+    /// as a table, or as JSON with `--dry-run=json`.
+    #[clap(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "table",
+        conflicts_with_all = ["batch", "watch"]
+    )]
+    #[arg(value_enum)]
+    dry_run: Option<DryRun>,
+
+    /// Use the settings of a named `[preset.<NAME>]` section from the config
+    /// files.
+    ///
+    /// Settings are taken from, in order: command line flags, environment
+    /// variables named after them like `QUICKSTITCH_MAX_HEIGHT`, the preset,
+    /// the top level of `./quickstitch.toml`, the top level of
+    /// `quickstitch/config.toml` in the user's config directory, and finally
+    /// the defaults. The preset itself can also be chosen with
+    /// `QUICKSTITCH_PRESET`.
+    #[clap(long)]
+    preset: Option<String>,
+
+    /// Print the final settings and where each came from, then exit.
+    #[clap(long)]
+    print_config: bool,
+
+    /// Write a JSON report of the run to this file.
+    ///
+    /// The report holds the final settings, the inputs loaded and skipped,
+    /// every file written with its size and source range, how long each step
+    /// took and every error. Its `version` only changes when existing fields
+    /// do.
+    #[clap(long, conflicts_with = "watch")]
+    report: Option<PathBuf>,
+
+    /// Print the JSON report described under `--report` to stdout once done.
+    #[clap(long, conflicts_with_all = ["watch", "dry_run"])]
+    json: bool,
+}
+
+/// A single chapter to stitch.
+enum Chapter {
+    Images(Vec<PathBuf>),
+    Dir(PathBuf),
+    Zip(PathBuf),
+    Plan(PathBuf),
+}
+
+/// Why a chapter could not be stitched, along with the exit code it maps to.
+struct Failure {
+    code: i32,
+    message: String,
+}
+
+impl Failure {
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
 }
 
 fn main() {
     env_logger::init();
-    let cli = Cli::parse();
+    let matches = Cli::command()
+        .try_get_matches()
+        .unwrap_or_else(|e| exit_usage(e));
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| exit_usage(e));
+    let origins = match config::apply(&mut cli, &matches) {
+        Ok(origins) => origins,
+        Err(e) => {
+            error!("{e:#}");
+            let mut report = RunReport::new(&cli);
+            report.errors.push(ErrorReport::new(format!("{e:#}")));
+            finish(&cli, &report, exitcode::CONFIG);
+        }
+    };
+    if cli.print_config {
+        config::print(&cli, &origins);
+        exit(exitcode::OK);
+    }
+    let mut report = RunReport::new(&cli);
+    if let Err(failure) = check_settings(&cli, &origins) {
+        error!("{}", failure.message);
+        report.errors.push(ErrorReport::new(failure.message));
+        finish(&cli, &report, failure.code);
+    }
 
-    let stitcher = Stitcher::new();
-    let now = Instant::now();
-    let loaded = match (cli.input.images, cli.input.dir) {
-        (Some(images), None) => {
-            let paths: Vec<&Path> = images.iter().map(PathBuf::as_path).collect();
-            stitcher.load(&paths, cli.width, true)
-        }
-        (None, Some(dir)) => stitcher.load_dir(
-            &dir,
-            cli.width,
-            true,
-            match cli.sort {
-                Sort::Natural => qs::Sort::Natural,
-                Sort::Logical => qs::Sort::Logical,
-            },
-        ),
-        _ => unimplemented!("arg group rules ensure only one of the two is provided"),
+    if let (true, Some(dir)) = (cli.watch, &cli.input.dir) {
+        watch(&cli, dir);
+    }
+    let input = cli.input.clone();
+    let chapter = match (
+        input.images,
+        input.dir,
+        input.zip,
+        input.plan_in,
+        input.batch,
+    ) {
+        (Some(images), None, None, None, None) => Chapter::Images(images),
+        (None, Some(dir), None, None, None) => Chapter::Dir(dir),
+        (None, None, Some(zip), None, None) => Chapter::Zip(zip),
+        (None, None, None, Some(plan_in), None) => Chapter::Plan(plan_in),
+        (None, None, None, None, Some(parent)) => batch(&cli, &parent, report),
+        (None, None, None, None, None) => exit_usage(Cli::command().error(
+            ErrorKind::MissingRequiredArgument,
+            "one of <IMAGES>, --dir, --zip, --plan-in or --batch is required",
+        )),
+        _ => unimplemented!("arg group rules ensure only one input is provided"),
     };
-    let loaded = match loaded {
-        Ok(stitcher) => {
-            info!("Images loaded successfully in {:?}", now.elapsed());
-            stitcher
+    let mut chapter_report = ChapterReport::default();
+    let result = stitch_chapter(&cli, chapter, &cli.output, &mut chapter_report);
+    let failure = result.err().map(|failure| {
+        error!("{}", failure.message);
+        chapter_report.error(failure.message);
+        failure.code
+    });
+    let code = exit_code(false, &[(failure, chapter_report.skipped.len())]);
+    report.chapters.push(chapter_report);
+    finish(&cli, &report, code);
+}
+
+/// Prints a command line parsing error, or the help and version text, and
+/// exits. Unlike [`clap::Error::exit`], bad arguments exit with
+/// `exitcode::USAGE` like every other usage error.
+fn exit_usage(e: clap::Error) -> ! {
+    let _ = e.print();
+    exit(if e.use_stderr() {
+        exitcode::USAGE
+    } else {
+        exitcode::OK
+    });
+}
+
+/// The code a run exits with, given the failure code, if any, and the number
+/// of skipped inputs of each chapter it stitched.
+///
+/// A single chapter exits with the code of its failure, while a batch only
+/// tells whether some or all of its chapters failed, as each may have failed
+/// for a different reason.
+fn exit_code(batch: bool, chapters: &[(Option<i32>, usize)]) -> i32 {
+    let failed = chapters.iter().filter(|(code, _)| code.is_some()).count();
+    let skipped = chapters.iter().any(|(_, skipped)| *skipped > 0);
+    match chapters {
+        [(Some(code), _)] if !batch => *code,
+        _ if failed > 0 && failed == chapters.len() => TOTAL_FAILURE,
+        _ if failed > 0 => PARTIAL_BATCH,
+        _ if skipped => SKIPPED_INPUTS,
+        _ => exitcode::OK,
+    }
+}
+
+/// Writes the report, if one was asked for, then exits with `code`.
+fn finish(cli: &Cli, report: &RunReport, code: i32) -> ! {
+    if let Err(e) = report.finish(cli) {
+        error!("{e:#}");
+        if code == exitcode::OK {
+            exit(exitcode::IOERR);
+        }
+    }
+    exit(code);
+}
+
+/// Stitches every chapter in `parent` into its own output subdirectory, then
+/// prints a summary and exits.
+fn batch(cli: &Cli, parent: &Path, mut report: RunReport) -> ! {
+    let chapters = match qs::find_chapters(parent, library_sort(&cli.sort)) {
+        Ok(chapters) if chapters.is_empty() => {
+            let message = format!(
+                "No chapter directories or archives found in {}",
+                parent.display()
+            );
+            error!("{message}");
+            report.errors.push(ErrorReport::new(message));
+            finish(cli, &report, exitcode::NOINPUT);
         }
+        Ok(chapters) => chapters,
         Err(e) => {
-            error!("Unable to load images: {e}");
-            exit(exitcode::IOERR);
+            let message = format!("Unable to find chapters: {e}");
+            error!("{message}");
+            report.errors.push(ErrorReport::new(message));
+            finish(cli, &report, exitcode::IOERR);
         }
     };
-    let now = Instant::now();
-    let stitched = loaded.stitch(
-        cli.max_height,
-        cli.min_height,
-        cli.scan_interval,
-        cli.sensitivity,
+
+    let results = stitch_chapters(cli, chapters, &mut report);
+    let outcomes: Vec<_> = results
+        .iter()
+        .map(|(_, result, skipped, _)| (result.as_ref().err().map(|f| f.code), *skipped))
+        .collect();
+    let code = exit_code(true, &outcomes);
+    let failed = outcomes.iter().filter(|(code, _)| code.is_some()).count();
+    // The report takes the place of the summary on stdout.
+    if cli.json {
+        finish(cli, &report, code);
+    }
+
+    let width = results
+        .iter()
+        .map(|(name, ..)| name.chars().count())
+        .fold("Chapter".len(), usize::max);
+    println!("{:width$}  Result", "Chapter");
+    for (name, result, skipped, elapsed) in &results {
+        match result {
+            Ok(pages) if *skipped > 0 => {
+                println!("{name:width$}  {pages} pages in {elapsed:.1?}, {skipped} inputs skipped")
+            }
+            Ok(pages) => println!("{name:width$}  {pages} pages in {elapsed:.1?}"),
+            Err(failure) => println!("{name:width$}  FAILED: {}", failure.message),
+        }
+    }
+    println!(
+        "{} of {} chapters stitched",
+        results.len() - failed,
+        results.len()
     );
-    info!("Splitpoints found in {:?}", now.elapsed());
-    let now = Instant::now();
+    finish(cli, &report, code);
+}
+
+/// How a chapter of a batch went: its output name, the number of pages
+/// written or why it failed, the number of inputs skipped and how long it
+/// took.
+type BatchResult = (String, Result<usize, Failure>, usize, Duration);
+
+/// Stitches every chapter into its own output subdirectory, recording each
+/// in the report.
+fn stitch_chapters(cli: &Cli, chapters: Vec<PathBuf>, report: &mut RunReport) -> Vec<BatchResult> {
+    let mut results = Vec::with_capacity(chapters.len());
+    for (path, name) in chapter_names(chapters) {
+        info!("Stitching {name}");
+        let now = Instant::now();
+        let chapter = match path.is_dir() {
+            true => Chapter::Dir(path.clone()),
+            false => Chapter::Zip(path.clone()),
+        };
+        let mut chapter_report = ChapterReport::default();
+        let result = stitch_chapter(cli, chapter, &cli.output.join(&name), &mut chapter_report);
+        if let Err(failure) = &result {
+            error!("{name}: {}", failure.message);
+            chapter_report.error(failure.message.clone());
+        }
+        let skipped = chapter_report.skipped.len();
+        report.chapters.push(chapter_report);
+        results.push((name, result, skipped, now.elapsed()));
+    }
+    results
+}
+
+/// Names the output subdirectory of each chapter: directories keep their name
+/// and archives lose their extension, unless that would give two chapters the
+/// same output, in which case the archive keeps its full file name.
+fn chapter_names(chapters: Vec<PathBuf>) -> Vec<(PathBuf, String)> {
+    let name = |path: &Path, stem: bool| {
+        match stem {
+            true => path.file_stem(),
+            false => path.file_name(),
+        }
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+    };
+    let stems: Vec<_> = chapters
+        .iter()
+        .map(|path| name(path, !path.is_dir()))
+        .collect();
+    // Compared case-insensitively, as the outputs may be on a filesystem that is.
+    let clashes = |stem: &str| {
+        stems
+            .iter()
+            .filter(|other| other.to_lowercase() == stem.to_lowercase())
+            .count()
+            > 1
+    };
+    chapters
+        .into_iter()
+        .zip(&stems)
+        .map(|(path, stem)| {
+            if path.is_dir() || !clashes(stem) {
+                return (path, stem.clone());
+            }
+            let unique = name(&path, false);
+            warn!(
+                "{} would share its output with another chapter, writing it to {unique} instead",
+                path.display()
+            );
+            (path, unique)
+        })
+        .collect()
+}
 
-    match std::fs::create_dir_all(&cli.output) {
-        Ok(_) => {}
+/// Stitches the chapter in `dir`, then re-stitches it every time its images
+/// change. Only returns if the directory can't be watched.
+fn watch(cli: &Cli, dir: &Path) -> ! {
+    // Exported pages would otherwise be picked up as new inputs.
+    if cli.output.canonicalize().ok() == dir.canonicalize().ok() {
+        error!("--output can't be the directory being watched");
+        exit(exitcode::USAGE);
+    }
+    if !library_on_existing(&cli.on_existing).allows_rebuilds() {
+        error!(
+            "--watch can't be used with --on-existing={}, use overwrite or clean",
+            cli.on_existing
+                .to_possible_value()
+                .map_or(String::new(), |value| value.get_name().to_string())
+        );
+        exit(exitcode::USAGE);
+    }
+    let (sender, receiver) = mpsc::channel();
+    let mut debouncer = match new_debouncer(WATCH_DEBOUNCE, None, sender) {
+        Ok(debouncer) => debouncer,
         Err(e) => {
-            error!("Unable to create output file directory: {e}");
-            exit(exitcode::IOERR);
+            error!("Unable to watch {}: {e}", dir.display());
+            exit(exitcode::OSERR);
+        }
+    };
+    if let Err(e) = debouncer.watch(dir, RecursiveMode::NonRecursive) {
+        error!("Unable to watch {}: {e}", dir.display());
+        exit(exitcode::IOERR);
+    }
+
+    let rebuild = || {
+        let mut report = ChapterReport::default();
+        match stitch_chapter(
+            cli,
+            Chapter::Dir(dir.to_path_buf()),
+            &cli.output,
+            &mut report,
+        ) {
+            Ok(pages) => info!("Stitched {pages} pages into {}", report.output.display()),
+            Err(failure) => error!("{}", failure.message),
+        }
+    };
+    rebuild();
+    info!("Watching {} for changes", dir.display());
+    for result in receiver {
+        let events = match result {
+            Ok(events) => events,
+            Err(errors) => {
+                for e in errors {
+                    warn!("Error while watching {}: {e}", dir.display());
+                }
+                continue;
+            }
+        };
+        // Reading the images while stitching shows up as access events, which
+        // would otherwise trigger a rebuild after every rebuild.
+        let changed = events
+            .iter()
+            .filter(|event| !matches!(event.kind, EventKind::Access(_)))
+            .flat_map(|event| &event.paths)
+            .filter(|path| qs::is_image(path))
+            .count();
+        if changed > 0 {
+            info!("Rebuilding after {changed} changed image(s)");
+            rebuild();
         }
     }
-    let errs = stitched.export(
-        &cli.output,
-        match cli.format {
-            ImageFormat::Png => qs::ImageOutputFormat::Png,
-            ImageFormat::Webp => qs::ImageOutputFormat::Webp,
-            ImageFormat::Jpg => qs::ImageOutputFormat::Jpg(cli.quality),
-            ImageFormat::Jpeg => qs::ImageOutputFormat::Jpeg(cli.quality),
+    error!("Stopped watching {}", dir.display());
+    exit(exitcode::IOERR);
+}
+
+fn library_sort(sort: &Sort) -> qs::Sort {
+    match sort {
+        Sort::Natural => qs::Sort::Natural,
+        Sort::Logical => qs::Sort::Logical,
+    }
+}
+
+fn library_on_existing(on_existing: &OnExisting) -> qs::OnExisting {
+    match on_existing {
+        OnExisting::Error => qs::OnExisting::Error,
+        OnExisting::Overwrite => qs::OnExisting::Overwrite,
+        OnExisting::Clean => qs::OnExisting::Clean,
+        OnExisting::NewDir => qs::OnExisting::NewDir,
+    }
+}
+
+/// Builds the job for a single chapter from the command line settings.
+fn stitch_job(cli: &Cli, input: qs::Input, output: &Path) -> StitchJob {
+    StitchJob {
+        input,
+        sort: library_sort(&cli.sort),
+        ignore_unloadable: !cli.strict,
+        output: output.to_path_buf(),
+        on_existing: library_on_existing(&cli.on_existing),
+        format: match cli.format {
+            ImageFormat::Png => qs::OutputFormat::Png,
+            ImageFormat::Webp => qs::OutputFormat::Webp,
+            ImageFormat::Jpg => qs::OutputFormat::Jpg(cli.quality),
+            ImageFormat::Jpeg => qs::OutputFormat::Jpeg(cli.quality),
         },
-        cli.debug,
+        comic_info: cli.archive.as_ref().map(|Archive::Cbz| ComicInfo {
+            series: cli.series.clone(),
+            number: cli.chapter.clone(),
+            title: cli.title.clone(),
+        }),
+        name_template: cli.name_template.clone(),
+        width: cli.width,
+        max_height: cli.max_height,
+        min_height: cli.min_height,
+        scan_interval: cli.scan_interval,
+        sensitivity: cli.sensitivity,
+        debug: cli.debug,
+    }
+}
+
+/// Checks the settings before any chapter is loaded, naming the flag and,
+/// if it didn't come from the command line, where the offending value came
+/// from.
+fn check_settings(cli: &Cli, origins: &[(&str, Origin)]) -> Result<(), Failure> {
+    let job = stitch_job(cli, qs::Input::Images(vec![]), &cli.output);
+    let Err(StitchError::InvalidSetting { setting, reason }) = job.validate_settings() else {
+        return Ok(());
+    };
+    let (id, value) = match setting {
+        Setting::Width => ("width", cli.width.unwrap_or_default().to_string()),
+        Setting::MaxHeight => ("max_height", cli.max_height.to_string()),
+        Setting::MinHeight => ("min_height", cli.min_height.to_string()),
+        Setting::ScanInterval => ("scan_interval", cli.scan_interval.to_string()),
+        Setting::Quality => ("quality", cli.quality.to_string()),
+        Setting::NameTemplate => ("name_template", format!("{:?}", cli.name_template)),
+    };
+    let mut message = format!(
+        "Invalid value {value} for --{}: {reason}",
+        id.replace('_', "-")
     );
-    match errs {
-        Ok(_) => info!("Images exported in {:?}", now.elapsed()),
+    if let Some((_, origin @ (Origin::Env(_) | Origin::Preset(..) | Origin::File(_)))) =
+        origins.iter().find(|(origin_id, _)| *origin_id == id)
+    {
+        message.push_str(&format!(" (set by {origin})"));
+    }
+    Err(Failure::new(exitcode::USAGE, message))
+}
+
+/// Loads, stitches and exports a single chapter into `output`, returning the
+/// number of pages written.
+///
+/// What was loaded and written is recorded in `report` as it happens, so it
+/// stays accurate when a later step fails.
+fn stitch_chapter(
+    cli: &Cli,
+    chapter: Chapter,
+    output: &Path,
+    report: &mut ChapterReport,
+) -> Result<usize, Failure> {
+    report.input = match &chapter {
+        Chapter::Images(_) => None,
+        Chapter::Dir(path) | Chapter::Zip(path) | Chapter::Plan(path) => Some(path.clone()),
+    };
+    report.output = output.to_path_buf();
+    let (input, plan) = match chapter {
+        Chapter::Images(images) => (qs::Input::Images(images), None),
+        Chapter::Dir(dir) => (qs::Input::Directory(dir), None),
+        Chapter::Zip(zip) => (qs::Input::Archive(zip), None),
+        Chapter::Plan(plan_in) => match Plan::read(&plan_in) {
+            Ok(plan) => (plan.input(), Some(plan)),
+            Err(e) => {
+                return Err(Failure::new(
+                    exitcode::DATAERR,
+                    format!("Unable to use plan: {e:#}"),
+                ));
+            }
+        },
+    };
+    let mut job = stitch_job(cli, input, output);
+    if let Some(plan) = &plan {
+        job.width = Some(plan.width);
+    }
+    let cancel = CancelToken::default();
+
+    let now = Instant::now();
+    let strip = match qs::load(&job, &cancel, &|_| {}) {
+        Ok(strip) => {
+            for skipped in &strip.skipped {
+                warn!("Skipping {}: {}", skipped.input.display(), skipped.reason);
+            }
+            info!("Images loaded successfully in {:?}", now.elapsed());
+            report.loaded(&strip, now.elapsed());
+            strip
+        }
         Err(e) => {
-            for err in e {
-                error!("Unable to export image: {err}");
+            let code = match e {
+                StitchError::InvalidSetting { .. } => exitcode::USAGE,
+                StitchError::ImageLoad { .. } | StitchError::StripTooLarge { .. } => {
+                    exitcode::DATAERR
+                }
+                StitchError::NoInputImages
+                | StitchError::NoLoadableImages
+                | StitchError::ReadDirectory { .. }
+                | StitchError::ReadArchive { .. }
+                | StitchError::MissingEntry { .. } => exitcode::NOINPUT,
+                _ => exitcode::IOERR,
+            };
+            return Err(Failure::new(code, format!("Unable to load images: {e}")));
+        }
+    };
+
+    let splitpoints = match plan {
+        Some(plan) => {
+            if plan.height != strip.image.height() as usize {
+                return Err(Failure::new(
+                    exitcode::DATAERR,
+                    format!(
+                        "The inputs no longer match the plan: expected a strip {} pixels tall, got {}",
+                        plan.height,
+                        strip.image.height()
+                    ),
+                ));
             }
-            exit(exitcode::IOERR);
+            info!("Using {} splitpoints from plan", plan.splitpoints.len());
+            qs::Splitpoints {
+                cuts: plan.splitpoints,
+                skipped: vec![],
+            }
+        }
+        None => {
+            let now = Instant::now();
+            let splitpoints = qs::find_splitpoints(&job, &strip);
+            info!("Splitpoints found in {:?}", now.elapsed());
+            report.timings.finding_splitpoints = Some(now.elapsed().as_secs_f64());
+            splitpoints
+        }
+    };
+
+    if let Some(plan_out) = &cli.plan_out {
+        let plan = Plan::new(
+            job.input.archive().cloned(),
+            strip.inputs.clone(),
+            strip.image.width(),
+            strip.image.height() as usize,
+            splitpoints.cuts.clone(),
+        );
+        match plan.write(plan_out) {
+            Ok(_) => info!("Plan written to {}", plan_out.display()),
+            Err(e) => return Err(Failure::new(exitcode::IOERR, format!("{e:#}"))),
+        }
+    }
+
+    if let Some(dry_run) = &cli.dry_run {
+        let inspection = Report::new(&strip, &splitpoints.cuts);
+        match dry_run {
+            DryRun::Table => inspection.print_table(),
+            DryRun::Json => match serde_json::to_string_pretty(&inspection) {
+                Ok(json) => println!("{json}"),
+                Err(e) => {
+                    return Err(Failure::new(
+                        exitcode::SOFTWARE,
+                        format!("Unable to serialize report: {e}"),
+                    ));
+                }
+            },
+        }
+        return Ok(inspection.pages.len());
+    }
+
+    let now = Instant::now();
+    match qs::export(&job, &strip, &splitpoints, &cancel, &|_| {}) {
+        Ok(files) => {
+            info!("Images exported in {:?}", now.elapsed());
+            // `--on-existing=new-dir` may have picked another directory.
+            if let Some(dir) = files.first().and_then(|file| file.parent()) {
+                report.output = dir.to_path_buf();
+            }
+            let kind = match job.comic_info {
+                Some(_) => OutputKind::Archive,
+                None => OutputKind::Pages,
+            };
+            report.exported(&strip, &splitpoints.cuts, kind, &files, now.elapsed());
+            Ok(splitpoints.cuts.len() + 1)
+        }
+        Err(e) => {
+            if let StitchError::Export(errors) = &e {
+                for e in errors {
+                    error!("{e}");
+                }
+                report.export_errors(errors);
+            }
+            Err(export_failure(e))
         }
     }
 }
+
+/// Maps an error from exporting a chapter to the code it exits with.
+fn export_failure(e: StitchError) -> Failure {
+    match e {
+        StitchError::Export(errors) => Failure::new(
+            exitcode::CANTCREAT,
+            format!("{} page(s) could not be exported", errors.len()),
+        ),
+        e @ StitchError::InvalidSplitpoints(_) => Failure::new(exitcode::DATAERR, e.to_string()),
+        e @ (StitchError::CreateOutputDirectory { .. }
+        | StitchError::OutputExists { .. }
+        | StitchError::CleanOutput { .. }) => Failure::new(OUTPUT_UNUSABLE, e.to_string()),
+        e => Failure::new(exitcode::IOERR, e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+    use std::fs::{self, File};
+    use std::io::{Cursor, Write};
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    fn cli(args: &[&str]) -> Cli {
+        Cli::try_parse_from(["quickstitch"].iter().chain(args)).unwrap()
+    }
+
+    /// Writes blank images that stitch into a single page.
+    fn write_images(dir: &Path, names: &[&str]) -> Vec<PathBuf> {
+        fs::create_dir_all(dir).unwrap();
+        names
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                RgbImage::new(100, 300).save(&path).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    /// Writes an archive of blank images.
+    fn write_archive(path: &Path, names: &[&str]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for name in names {
+            let mut png = Cursor::new(vec![]);
+            RgbImage::new(100, 300)
+                .write_to(&mut png, image::ImageFormat::Png)
+                .unwrap();
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(png.get_ref()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    /// The output names of the chapters found in `parent`.
+    fn names(parent: &Path) -> Vec<String> {
+        let chapters = qs::find_chapters(parent, qs::Sort::Natural).unwrap();
+        chapter_names(chapters)
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect()
+    }
+
+    /// The code a single chapter stitched with `args` exits with.
+    fn chapter_code(args: &[&str], chapter: Chapter, output: &Path) -> i32 {
+        let cli = cli(args);
+        let mut report = ChapterReport::default();
+        let result = stitch_chapter(&cli, chapter, output, &mut report);
+        exit_code(
+            false,
+            &[(result.err().map(|f| f.code), report.skipped.len())],
+        )
+    }
+
+    #[test]
+    fn single_chapters_exit_with_the_code_of_their_failure() {
+        assert_eq!(exit_code(false, &[(None, 0)]), exitcode::OK);
+        assert_eq!(exit_code(false, &[(None, 2)]), SKIPPED_INPUTS);
+        for code in [OUTPUT_UNUSABLE, exitcode::DATAERR, exitcode::CANTCREAT] {
+            assert_eq!(exit_code(false, &[(Some(code), 0)]), code);
+        }
+    }
+
+    #[test]
+    fn batches_exit_with_how_many_chapters_failed() {
+        let ok = (None, 0);
+        let skipped = (None, 1);
+        let failed = (Some(exitcode::NOINPUT), 0);
+        assert_eq!(exit_code(true, &[ok, ok]), exitcode::OK);
+        assert_eq!(exit_code(true, &[ok, skipped]), SKIPPED_INPUTS);
+        assert_eq!(exit_code(true, &[skipped, failed]), PARTIAL_BATCH);
+        assert_eq!(exit_code(true, &[failed, failed]), TOTAL_FAILURE);
+        // A batch of one still reports it as a batch.
+        assert_eq!(exit_code(true, &[failed]), TOTAL_FAILURE);
+    }
+
+    #[test]
+    fn stitched_and_skipped_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("chapter");
+        write_images(&input, &["1.png", "2.png"]);
+        let output = dir.path().join("stitched");
+        let chapter = Chapter::Dir(input.clone());
+        assert_eq!(chapter_code(&[], chapter, &output), exitcode::OK);
+
+        fs::write(input.join("3.png"), "not an image").unwrap();
+        let chapter = Chapter::Dir(input.clone());
+        assert_eq!(chapter_code(&[], chapter, &output), SKIPPED_INPUTS);
+        let chapter = Chapter::Dir(input);
+        assert_eq!(
+            chapter_code(&["--strict"], chapter, &output),
+            exitcode::DATAERR
+        );
+    }
+
+    #[test]
+    fn invalid_settings_are_usage_errors() {
+        let cli = cli(&["--min-height", "20000", "--max-height", "10000"]);
+        let failure = check_settings(&cli, &[]).unwrap_err();
+        assert_eq!(failure.code, exitcode::USAGE);
+        assert!(
+            failure.message.contains("--min-height"),
+            "{}",
+            failure.message
+        );
+    }
+
+    #[test]
+    fn missing_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("empty");
+        fs::create_dir(&input).unwrap();
+        let output = dir.path().join("stitched");
+        let chapter = Chapter::Dir(input);
+        assert_eq!(chapter_code(&[], chapter, &output), exitcode::NOINPUT);
+        let chapter = Chapter::Dir(dir.path().join("missing"));
+        assert_eq!(chapter_code(&[], chapter, &output), exitcode::NOINPUT);
+    }
+
+    #[test]
+    fn unusable_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let images = write_images(&dir.path().join("chapter"), &["1.png"]);
+        let chapter = || Chapter::Images(images.clone());
+
+        let taken = dir.path().join("taken");
+        fs::create_dir(&taken).unwrap();
+        fs::write(taken.join("notes.txt"), "mine").unwrap();
+        let args = ["--on-existing", "error"];
+        assert_eq!(chapter_code(&args, chapter(), &taken), OUTPUT_UNUSABLE);
+
+        let file = dir.path().join("file");
+        fs::write(&file, "not a directory").unwrap();
+        assert_eq!(chapter_code(&[], chapter(), &file), OUTPUT_UNUSABLE);
+    }
+
+    #[test]
+    fn pages_that_fail_to_export() {
+        let error = qs::ExportError {
+            page: Some(2),
+            path: PathBuf::from("stitched/page_2.jpg"),
+            reason: "disk full".to_string(),
+        };
+        let failure = export_failure(StitchError::Export(vec![error]));
+        assert_eq!(failure.code, exitcode::CANTCREAT);
+        assert_eq!(failure.message, "1 page(s) could not be exported");
+        let invalid = StitchError::InvalidSplitpoints("0 must be greater than 0".to_string());
+        assert_eq!(export_failure(invalid).code, exitcode::DATAERR);
+    }
+
+    #[test]
+    fn unwritable_plans() {
+        let dir = tempfile::tempdir().unwrap();
+        let images = write_images(&dir.path().join("chapter"), &["1.png"]);
+        let plan = dir.path().join("missing").join("plan.json");
+        let args = ["--plan-out", plan.to_str().unwrap()];
+        let output = dir.path().join("stitched");
+        let code = chapter_code(&args, Chapter::Images(images), &output);
+        assert_eq!(code, exitcode::IOERR);
+    }
+
+    #[test]
+    fn batches_find_chapter_directories_and_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        for chapter in ["1", "10"] {
+            fs::create_dir(dir.path().join(chapter)).unwrap();
+        }
+        write_archive(&dir.path().join("2.cbz"), &["1.png"]);
+        write_archive(&dir.path().join("3.ZIP"), &["1.png"]);
+        fs::write(dir.path().join("notes.txt"), "not a chapter").unwrap();
+        fs::write(dir.path().join("4.png"), "not a chapter either").unwrap();
+        assert_eq!(names(dir.path()), ["1", "2", "3", "10"]);
+    }
+
+    #[test]
+    fn archives_keep_their_extension_when_names_clash() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("Extra")).unwrap();
+        fs::create_dir(dir.path().join("1")).unwrap();
+        for archive in ["1.cbz", "2.cbz", "2.zip", "extra.zip", "3.cbz"] {
+            write_archive(&dir.path().join(archive), &["1.png"]);
+        }
+        assert_eq!(
+            names(dir.path()),
+            ["1", "1.cbz", "2.cbz", "2.zip", "3", "Extra", "extra.zip"]
+        );
+    }
+
+    #[test]
+    fn batches_stitch_every_chapter_they_can() {
+        let dir = tempfile::tempdir().unwrap();
+        let parent = dir.path().join("series");
+        write_images(&parent.join("1"), &["1.png", "2.png"]);
+        write_archive(&parent.join("2.cbz"), &["1.png", "2.png"]);
+        // Nothing in here can be loaded.
+        fs::create_dir(parent.join("3")).unwrap();
+        fs::write(parent.join("3").join("notes.txt"), "not an image").unwrap();
+        let output = dir.path().join("stitched");
+        let cli = cli(&[
+            "--output",
+            output.to_str().unwrap(),
+            "--format",
+            "png",
+            "--batch",
+            parent.to_str().unwrap(),
+        ]);
+
+        let mut report = RunReport::new(&cli);
+        let chapters = qs::find_chapters(&parent, qs::Sort::Natural).unwrap();
+        let results = stitch_chapters(&cli, chapters, &mut report);
+        let outcomes: Vec<_> = results
+            .iter()
+            .map(|(name, result, ..)| (name.as_str(), result.as_ref().copied().map_err(|f| f.code)))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [("1", Ok(1)), ("2", Ok(1)), ("3", Err(exitcode::NOINPUT))]
+        );
+        assert!(output.join("1").join("page_1.png").is_file());
+        assert!(output.join("2").join("page_1.png").is_file());
+        assert!(!output.join("3").exists());
+
+        assert_eq!(report.chapters.len(), 3);
+        assert!(
+            report.chapters[..2]
+                .iter()
+                .all(|chapter| chapter.errors.is_empty())
+        );
+        assert_eq!(report.chapters[2].errors.len(), 1);
+        let codes: Vec<_> = results
+            .iter()
+            .map(|(_, result, skipped, _)| (result.as_ref().err().map(|f| f.code), *skipped))
+            .collect();
+        assert_eq!(exit_code(true, &codes), PARTIAL_BATCH);
+    }
+}
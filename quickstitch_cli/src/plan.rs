@@ -0,0 +1,227 @@
+use std::{
+    fs,
+    path::{self, Component, Path, PathBuf},
+};
+
+use anyhow::{Context, Result, ensure};
+use quickstitch_app::Input;
+use serde::{Deserialize, Serialize};
+
+const PLAN_VERSION: u32 = 1;
+
+/// The cut plan for a chapter, as written by `--plan-out` and read back by
+/// `--plan-in`.
+///
+/// Splitpoints are rows of the stitched strip at which a new page starts, so
+/// hand-editing a plan only requires moving, adding or removing numbers.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Plan {
+    pub version: u32,
+    /// The archive the inputs were read from, if any. Like the inputs, it is
+    /// stored relative to the directory the plan is in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<PathBuf>,
+    /// The images that make up the strip, in order. When `archive` is set,
+    /// these are entry names within the archive.
+    pub inputs: Vec<PathBuf>,
+    /// The width every image was resized to.
+    pub width: u32,
+    /// The height of the stitched strip.
+    pub height: usize,
+    pub splitpoints: Vec<usize>,
+}
+
+impl Plan {
+    pub fn new(
+        archive: Option<PathBuf>,
+        inputs: Vec<PathBuf>,
+        width: u32,
+        height: usize,
+        splitpoints: Vec<usize>,
+    ) -> Self {
+        Self {
+            version: PLAN_VERSION,
+            archive,
+            inputs,
+            width,
+            height,
+            splitpoints,
+        }
+    }
+
+    /// Reads a plan from disk, checking that its splitpoints are usable.
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Unable to read plan {}", path.display()))?;
+        let mut plan: Plan = serde_json::from_str(&contents)
+            .with_context(|| format!("Unable to parse plan {}", path.display()))?;
+        ensure!(
+            plan.version == PLAN_VERSION,
+            "Unsupported plan version {} (expected {PLAN_VERSION})",
+            plan.version
+        );
+        plan.resolve_from(path.parent().unwrap_or(Path::new("")));
+        ensure!(!plan.inputs.is_empty(), "The plan does not list any inputs");
+        ensure!(plan.width > 0, "The plan width must be greater than 0");
+        let mut previous = 0;
+        for &splitpoint in &plan.splitpoints {
+            ensure!(
+                splitpoint > previous && splitpoint < plan.height,
+                "Splitpoint {splitpoint} must be greater than {previous} and less than the strip height ({})",
+                plan.height
+            );
+            previous = splitpoint;
+        }
+        Ok(plan)
+    }
+
+    /// The plan's inputs in order, read out of the archive when there is one.
+    pub fn input(&self) -> Input {
+        match &self.archive {
+            Some(archive) => Input::ArchiveEntries(archive.clone(), self.inputs.clone()),
+            None => Input::Images(self.inputs.clone()),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let dir = path::absolute(path.parent().unwrap_or(Path::new("")))
+            .with_context(|| format!("Unable to write plan {}", path.display()))?;
+        let relative = |file: &PathBuf| relative_to(file, &dir);
+        let plan = Plan {
+            version: self.version,
+            archive: self.archive.as_ref().map(relative),
+            inputs: match self.archive {
+                Some(_) => self.inputs.clone(),
+                None => self.inputs.iter().map(relative).collect(),
+            },
+            width: self.width,
+            height: self.height,
+            splitpoints: self.splitpoints.clone(),
+        };
+        let contents = serde_json::to_string_pretty(&plan)?;
+        fs::write(path, contents)
+            .with_context(|| format!("Unable to write plan {}", path.display()))
+    }
+
+    /// Turns paths stored relative to the plan into ones that can be opened.
+    fn resolve_from(&mut self, dir: &Path) {
+        let resolve = |path: &mut PathBuf| {
+            let mut resolved = PathBuf::new();
+            for component in dir.join(&*path).components() {
+                match component {
+                    Component::ParentDir if resolved.file_name().is_some() => {
+                        resolved.pop();
+                    }
+                    component => resolved.push(component),
+                }
+            }
+            *path = resolved;
+        };
+        match &mut self.archive {
+            Some(archive) => resolve(archive),
+            None => self.inputs.iter_mut().for_each(resolve),
+        }
+    }
+}
+
+/// The path as seen from `dir`, which must be absolute. Paths on another
+/// drive or root are kept absolute.
+fn relative_to(path: &Path, dir: &Path) -> PathBuf {
+    let Ok(path) = path::absolute(path) else {
+        return path.to_path_buf();
+    };
+    let mut path_components = path.components().peekable();
+    let mut dir_components = dir.components().peekable();
+    if path_components.peek() != dir_components.peek() {
+        return path;
+    }
+    while path_components.peek().is_some() && path_components.peek() == dir_components.peek() {
+        path_components.next();
+        dir_components.next();
+    }
+    dir_components
+        .map(|_| Component::ParentDir)
+        .chain(path_components)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(splitpoints: Vec<usize>) -> Plan {
+        Plan::new(None, vec![PathBuf::from("1.png")], 100, 1000, splitpoints)
+    }
+
+    /// Writes the plan as-is, bypassing the path rewriting of [`Plan::write`].
+    fn read_raw(dir: &Path, plan: &Plan) -> Result<Plan> {
+        let path = dir.join("plan.json");
+        fs::write(&path, serde_json::to_string(plan).unwrap()).unwrap();
+        Plan::read(&path)
+    }
+
+    fn error(result: Result<Plan>) -> String {
+        format!("{:#}", result.unwrap_err())
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut future = plan(vec![]);
+        future.version = PLAN_VERSION + 1;
+        assert!(
+            error(read_raw(dir.path(), &future))
+                .contains(&format!("Unsupported plan version {}", PLAN_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn rejects_plans_without_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut empty = plan(vec![]);
+        empty.inputs.clear();
+        assert!(error(read_raw(dir.path(), &empty)).contains("does not list any inputs"));
+    }
+
+    #[test]
+    fn splitpoints_must_increase_within_the_strip() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(read_raw(dir.path(), &plan(vec![200, 500, 999])).is_ok());
+        for splitpoints in [vec![0], vec![500, 500], vec![500, 200], vec![1000]] {
+            let message = error(read_raw(dir.path(), &plan(splitpoints.clone())));
+            assert!(message.contains("must be greater than"), "{splitpoints:?}");
+        }
+    }
+
+    #[test]
+    fn inputs_are_stored_relative_to_the_plan() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("images").join("1.png");
+        let plan_path = dir.path().join("plans").join("plan.json");
+        fs::create_dir(plan_path.parent().unwrap()).unwrap();
+        let mut original = plan(vec![500]);
+        original.inputs = vec![image.clone()];
+        original.write(&plan_path).unwrap();
+
+        let stored: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&plan_path).unwrap()).unwrap();
+        let expected = Path::new("..").join("images").join("1.png");
+        assert_eq!(stored["inputs"][0], expected.to_str().unwrap());
+        assert_eq!(Plan::read(&plan_path).unwrap().inputs, [image]);
+    }
+
+    #[test]
+    fn archive_entries_stay_as_they_are() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("chapter.cbz");
+        let plan_path = dir.path().join("plan.json");
+        let mut original = plan(vec![]);
+        original.archive = Some(archive.clone());
+        original.inputs = vec![PathBuf::from("pages/1.png")];
+        original.write(&plan_path).unwrap();
+
+        let read = Plan::read(&plan_path).unwrap();
+        assert_eq!(read.archive, Some(archive));
+        assert_eq!(read.inputs, [PathBuf::from("pages/1.png")]);
+    }
+}
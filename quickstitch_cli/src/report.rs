@@ -0,0 +1,404 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use quickstitch_app::{ExportError, Strip};
+use serde::Serialize;
+
+use crate::{Archive, Cli, ImageFormat, OnExisting, Sort, inspect::SourceRow};
+
+/// Bumped whenever a field is removed or changes meaning, so tools reading
+/// `--report`/`--json` output can tell which layout they got. Adding fields
+/// doesn't change the version.
+const REPORT_VERSION: u32 = 1;
+
+/// Everything a run did, as written by `--report` and `--json`.
+#[derive(Debug, Serialize)]
+pub struct RunReport<'a> {
+    pub version: u32,
+    pub settings: ReportSettings<'a>,
+    pub chapters: Vec<ChapterReport>,
+    /// Errors that stopped the run before any chapter was stitched.
+    pub errors: Vec<ErrorReport>,
+}
+
+/// The settings every chapter was stitched with, after config files, presets
+/// and environment variables were applied.
+#[derive(Debug, Serialize)]
+pub struct ReportSettings<'a> {
+    pub output: &'a Path,
+    pub on_existing: &'a OnExisting,
+    pub sort: &'a Sort,
+    pub max_height: usize,
+    pub min_height: usize,
+    pub scan_interval: usize,
+    pub sensitivity: u8,
+    pub format: &'a ImageFormat,
+    pub quality: u8,
+    pub width: Option<u32>,
+    pub strict: bool,
+    pub name_template: &'a str,
+    pub debug: bool,
+    pub archive: Option<&'a Archive>,
+    /// The `ComicInfo.xml` metadata written with `--archive`.
+    pub series: Option<&'a str>,
+    pub chapter: Option<&'a str>,
+    pub title: Option<&'a str>,
+    pub preset: Option<&'a str>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ChapterReport {
+    /// The directory, archive or plan the chapter was read from, or `None` for
+    /// a list of images.
+    pub input: Option<PathBuf>,
+    /// Where the files were written, which `--on-existing=new-dir` may have
+    /// moved away from the output directory asked for.
+    pub output: PathBuf,
+    /// The inputs that made it into the strip, in order.
+    pub loaded: Vec<PathBuf>,
+    pub skipped: Vec<SkippedReport>,
+    /// Whether `files` are loose pages or a single archive. Left out when
+    /// nothing was written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<OutputKind>,
+    pub files: Vec<FileReport>,
+    pub timings: Timings,
+    pub errors: Vec<ErrorReport>,
+}
+
+/// What a chapter was written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputKind {
+    /// A file for every page.
+    Pages,
+    /// A single archive holding every page, as written with `--archive`.
+    Archive,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkippedReport {
+    pub input: PathBuf,
+    pub reason: String,
+}
+
+/// A file written to the output directory.
+#[derive(Debug, Serialize)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub width: u32,
+    /// The height of the page, or of every page together for an archive.
+    pub height: usize,
+    pub bytes: u64,
+    /// The input and row the file starts at.
+    pub from: SourceRow,
+    /// The input and row the file ends at, inclusive.
+    pub to: SourceRow,
+}
+
+/// How long each step took, in seconds. Steps that didn't run are left out.
+#[derive(Debug, Default, Serialize)]
+pub struct Timings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loading: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finding_splitpoints: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exporting: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    pub message: String,
+    /// The page that failed to export, counting from 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<usize>,
+    /// The file that failed to export.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+impl<'a> RunReport<'a> {
+    pub fn new(cli: &'a Cli) -> Self {
+        Self {
+            version: REPORT_VERSION,
+            settings: ReportSettings {
+                output: &cli.output,
+                on_existing: &cli.on_existing,
+                sort: &cli.sort,
+                max_height: cli.max_height,
+                min_height: cli.min_height,
+                scan_interval: cli.scan_interval,
+                sensitivity: cli.sensitivity,
+                format: &cli.format,
+                quality: cli.quality,
+                width: cli.width,
+                strict: cli.strict,
+                name_template: &cli.name_template,
+                debug: cli.debug,
+                archive: cli.archive.as_ref(),
+                series: cli.series.as_deref(),
+                chapter: cli.chapter.as_deref(),
+                title: cli.title.as_deref(),
+                preset: cli.preset.as_deref(),
+            },
+            chapters: vec![],
+            errors: vec![],
+        }
+    }
+
+    /// Writes the report to the `--report` file and prints it for `--json`,
+    /// whichever were asked for.
+    pub fn finish(&self, cli: &Cli) -> Result<()> {
+        if cli.report.is_none() && !cli.json {
+            return Ok(());
+        }
+        let json = serde_json::to_string_pretty(self).context("Unable to serialize report")?;
+        if let Some(path) = &cli.report {
+            fs::write(path, &json)
+                .with_context(|| format!("Unable to write report {}", path.display()))?;
+        }
+        if cli.json {
+            println!("{json}");
+        }
+        Ok(())
+    }
+}
+
+impl ChapterReport {
+    pub fn loaded(&mut self, strip: &Strip, took: Duration) {
+        self.loaded = strip.inputs.clone();
+        self.skipped = strip
+            .skipped
+            .iter()
+            .map(|skipped| SkippedReport {
+                input: skipped.input.clone(),
+                reason: skipped.reason.clone(),
+            })
+            .collect();
+        self.timings.loading = Some(took.as_secs_f64());
+    }
+
+    /// Records the written files. Loose pages get a file each, while an
+    /// archive holds the whole strip.
+    pub fn exported(
+        &mut self,
+        strip: &Strip,
+        cuts: &[usize],
+        kind: OutputKind,
+        files: &[PathBuf],
+        took: Duration,
+    ) {
+        let height = strip.image.height() as usize;
+        let bounds: Vec<usize> = [0]
+            .into_iter()
+            .chain(cuts.iter().copied())
+            .chain([height])
+            .collect();
+        let ranges: Vec<(usize, usize)> = match kind {
+            OutputKind::Archive => vec![(0, height)],
+            OutputKind::Pages => bounds.windows(2).map(|page| (page[0], page[1])).collect(),
+        };
+        self.kind = Some(kind);
+        self.files = files
+            .iter()
+            .zip(ranges)
+            .map(|(path, (start, end))| FileReport {
+                path: path.clone(),
+                width: strip.image.width(),
+                height: end - start,
+                bytes: fs::metadata(path)
+                    .map(|metadata| metadata.len())
+                    .unwrap_or(0),
+                from: SourceRow::new(strip.locate(start)),
+                to: SourceRow::new(strip.locate(end - 1)),
+            })
+            .collect();
+        self.timings.exporting = Some(took.as_secs_f64());
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.errors.push(ErrorReport::new(message));
+    }
+
+    pub fn export_errors(&mut self, errors: &[ExportError]) {
+        self.errors.extend(errors.iter().map(|e| ErrorReport {
+            message: e.reason.clone(),
+            page: e.page,
+            path: Some(e.path.clone()),
+        }));
+    }
+}
+
+impl ErrorReport {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            page: None,
+            path: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use image::RgbImage;
+    use serde_json::{Value, json};
+
+    /// A strip of two 100 pixel tall inputs.
+    fn strip() -> Strip {
+        Strip {
+            image: RgbImage::new(10, 200),
+            inputs: vec![PathBuf::from("1.png"), PathBuf::from("2.png")],
+            offsets: vec![0, 100],
+            skipped: vec![],
+        }
+    }
+
+    fn to_json(value: &impl Serialize) -> Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    #[test]
+    fn settings_include_every_flag_that_changes_the_output() {
+        let cli = Cli::try_parse_from([
+            "quickstitch",
+            "--strict",
+            "--archive",
+            "cbz",
+            "--series",
+            "Series",
+            "--chapter",
+            "7",
+            "--title",
+            "Title",
+        ])
+        .unwrap();
+        let report = to_json(&RunReport::new(&cli));
+        assert_eq!(report["version"], REPORT_VERSION);
+        let settings = &report["settings"];
+        let keys: Vec<&str> = settings
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        // The keys come back sorted.
+        assert_eq!(
+            keys,
+            [
+                "archive",
+                "chapter",
+                "debug",
+                "format",
+                "max_height",
+                "min_height",
+                "name_template",
+                "on_existing",
+                "output",
+                "preset",
+                "quality",
+                "scan_interval",
+                "sensitivity",
+                "series",
+                "sort",
+                "strict",
+                "title",
+                "width",
+            ]
+        );
+        assert_eq!(settings["strict"], true);
+        assert_eq!(settings["archive"], "cbz");
+        assert_eq!(settings["series"], "Series");
+        assert_eq!(settings["chapter"], "7");
+        assert_eq!(settings["title"], "Title");
+        assert_eq!(report["chapters"], json!([]));
+        assert_eq!(report["errors"], json!([]));
+    }
+
+    #[test]
+    fn pages_cover_the_strip_between_cuts() {
+        let mut chapter = ChapterReport::default();
+        let files = [PathBuf::from("page_1.png"), PathBuf::from("page_2.png")];
+        chapter.exported(&strip(), &[150], OutputKind::Pages, &files, Duration::ZERO);
+        let report = to_json(&chapter);
+        assert_eq!(report["kind"], "pages");
+        assert_eq!(
+            report["files"],
+            json!([
+                {
+                    "path": "page_1.png",
+                    "width": 10,
+                    "height": 150,
+                    "bytes": 0,
+                    "from": { "input": "1.png", "row": 0 },
+                    "to": { "input": "2.png", "row": 49 },
+                },
+                {
+                    "path": "page_2.png",
+                    "width": 10,
+                    "height": 50,
+                    "bytes": 0,
+                    "from": { "input": "2.png", "row": 50 },
+                    "to": { "input": "2.png", "row": 99 },
+                },
+            ])
+        );
+        assert_eq!(report["timings"], json!({ "exporting": 0.0 }));
+    }
+
+    #[test]
+    fn an_archive_covers_the_whole_strip() {
+        let mut chapter = ChapterReport::default();
+        let files = [PathBuf::from("chapter.cbz")];
+        chapter.exported(
+            &strip(),
+            &[150],
+            OutputKind::Archive,
+            &files,
+            Duration::ZERO,
+        );
+        let report = to_json(&chapter);
+        assert_eq!(report["kind"], "archive");
+        assert_eq!(report["files"][0]["height"], 200);
+        assert_eq!(
+            report["files"][0]["to"],
+            json!({ "input": "2.png", "row": 99 })
+        );
+    }
+
+    #[test]
+    fn a_single_page_is_still_reported_as_pages() {
+        let mut chapter = ChapterReport::default();
+        let files = [PathBuf::from("page_1.png")];
+        chapter.exported(&strip(), &[], OutputKind::Pages, &files, Duration::ZERO);
+        assert_eq!(to_json(&chapter)["kind"], "pages");
+    }
+
+    #[test]
+    fn chapters_that_failed_leave_out_what_never_happened() {
+        let mut chapter = ChapterReport::default();
+        chapter.export_errors(&[ExportError {
+            page: Some(2),
+            path: PathBuf::from("page_2.png"),
+            reason: "disk full".to_string(),
+        }]);
+        chapter.error("1 page(s) could not be exported");
+        let report = to_json(&chapter);
+        assert!(report.get("kind").is_none());
+        assert_eq!(report["timings"], json!({}));
+        assert_eq!(
+            report["errors"],
+            json!([
+                { "message": "disk full", "page": 2, "path": "page_2.png" },
+                { "message": "1 page(s) could not be exported" },
+            ])
+        );
+    }
+}
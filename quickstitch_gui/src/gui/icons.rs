@@ -19,9 +19,16 @@ macro_rules! add_icon {
     };
 }
 
+add_icon!(archive_icon, '\u{E909}');
+add_icon!(arrow_down_icon, '\u{E90A}');
+add_icon!(arrow_up_icon, '\u{E912}');
+add_icon!(chevrons_down_icon, '\u{E943}');
+add_icon!(chevrons_up_icon, '\u{E946}');
 add_icon!(folder_icon, '\u{E950}');
 add_icon!(add_folder_icon, '\u{E952}');
 add_icon!(add_file_icon, '\u{E96F}');
 add_icon!(image_icon, '\u{E978}');
+add_icon!(list_icon, '\u{E984}');
+add_icon!(move_icon, '\u{E9BC}');
 add_icon!(settings_icon, '\u{E9DB}');
 add_icon!(delete_icon, '\u{E9F6}');
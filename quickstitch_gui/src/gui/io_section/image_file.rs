@@ -1,36 +1,230 @@
-use std::path::PathBuf;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::SystemTime,
+};
 
 use iced::{
     Element,
-    widget::{button, row, text},
+    Length::Fill,
+    futures::{Stream, channel::mpsc},
+    mouse::Interaction,
+    widget::{Space, Text, button, column, container, image, mouse_area, row, text},
+};
+
+use crate::gui::{
+    icons::{
+        arrow_down_icon, arrow_up_icon, chevrons_down_icon, chevrons_up_icon, delete_icon,
+        move_icon,
+    },
+    style,
 };
 
-use crate::gui::icons::delete_icon;
+/// The size of the box thumbnails are scaled down to fit in.
+const THUMBNAIL_SIZE: u32 = 64;
+/// How far, as a fraction of the typical width, an image's width may differ
+/// before it is flagged. Stitching resizes every image to one width, so
+/// outliers usually end up blurry or are not pages at all.
+const WIDTH_TOLERANCE: f32 = 0.2;
 
 pub struct ImageFile {
+    key: ImageKey,
+}
+
+/// An input image as it was on disk when it was added, so that an image that
+/// has changed since is inspected again instead of showing stale details.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImageKey {
     path: PathBuf,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl ImageKey {
+    pub fn new(path: PathBuf) -> Self {
+        let metadata = fs::metadata(&path).ok();
+        Self {
+            modified: metadata
+                .as_ref()
+                .and_then(|metadata| metadata.modified().ok()),
+            len: metadata.map_or(0, |metadata| metadata.len()),
+            path,
+        }
+    }
+}
+
+/// What's known about an input image once it has been decoded.
+#[derive(Debug, Clone)]
+pub struct ImageInfo {
+    thumbnail: image::Handle,
+    pub width: u32,
+    height: u32,
+    file_size: u64,
+    format: String,
 }
 
 #[derive(Clone, Debug)]
 pub enum ImageFileMessage {
     Delete,
+    MoveUp,
+    MoveDown,
+    MoveToTop,
+    MoveToBottom,
+    /// The drag handle was pressed.
+    StartDrag,
+    /// The cursor entered the row, possibly while dragging another image.
+    DragOver,
 }
 
 impl ImageFile {
     pub fn path(&self) -> PathBuf {
-        self.path.clone()
+        self.key.path.clone()
     }
-    pub fn with_path(path: PathBuf) -> Self {
-        Self { path }
+    pub fn key(&self) -> &ImageKey {
+        &self.key
     }
-    pub fn view(&self) -> Element<ImageFileMessage> {
-        row![
-            text(self.path.file_name().unwrap().display().to_string()).size(20),
-            button(delete_icon().size(20))
-                .style(button::danger)
-                .on_press(ImageFileMessage::Delete)
-        ]
-        .spacing(20)
+    pub fn with_key(key: ImageKey) -> Self {
+        Self { key }
+    }
+    /// `info` is `None` while the image is still being inspected, and
+    /// `typical_width` is the width most of the other images share.
+    ///
+    /// `first` and `last` disable the moves that would go past the ends of the
+    /// list, while `dragged` highlights the image being dragged.
+    pub fn view(
+        &self,
+        info: Option<&Result<ImageInfo, String>>,
+        typical_width: Option<u32>,
+        first: bool,
+        last: bool,
+        dragged: bool,
+    ) -> Element<ImageFileMessage> {
+        let move_button = |icon: Text<'static>, message, enabled: bool| {
+            button(icon.size(20))
+                .style(button::text)
+                .on_press_maybe(enabled.then_some(message))
+        };
+        let name = self
+            .key
+            .path
+            .file_name()
+            .unwrap_or(self.key.path.as_os_str())
+            .display()
+            .to_string();
+
+        let (thumbnail, details, flag): (Element<_>, _, _) = match info {
+            None => (
+                Space::with_width(THUMBNAIL_SIZE as f32).into(),
+                "Loading…".to_string(),
+                None,
+            ),
+            Some(Ok(info)) => (
+                image(info.thumbnail.clone()).into(),
+                format!(
+                    "{} × {} · {} · {}",
+                    info.width,
+                    info.height,
+                    file_size(info.file_size),
+                    info.format
+                ),
+                typical_width
+                    .filter(|&typical| {
+                        info.width.abs_diff(typical) as f32 > typical as f32 * WIDTH_TOLERANCE
+                    })
+                    .map(|typical| {
+                        text(format!(
+                            "Much narrower or wider than the others ({typical} pixels)"
+                        ))
+                        .style(style::warning_text)
+                    }),
+            ),
+            Some(Err(reason)) => (
+                Space::with_width(THUMBNAIL_SIZE as f32).into(),
+                String::new(),
+                Some(text(format!("Can't be loaded: {reason}")).style(style::danger_text)),
+            ),
+        };
+        let mut description = column![
+            text(name).size(20).style(if dragged {
+                text::primary
+            } else {
+                text::default
+            }),
+            text(details).size(16).style(text::secondary),
+        ];
+        if let Some(flag) = flag {
+            description = description.push(flag.size(16));
+        }
+
+        mouse_area(
+            row![
+                mouse_area(move_icon().size(20))
+                    .on_press(ImageFileMessage::StartDrag)
+                    .interaction(Interaction::Grab),
+                container(thumbnail).center(THUMBNAIL_SIZE as f32),
+                description.width(Fill),
+                move_button(chevrons_up_icon(), ImageFileMessage::MoveToTop, !first),
+                move_button(arrow_up_icon(), ImageFileMessage::MoveUp, !first),
+                move_button(arrow_down_icon(), ImageFileMessage::MoveDown, !last),
+                move_button(chevrons_down_icon(), ImageFileMessage::MoveToBottom, !last),
+                button(delete_icon().size(20))
+                    .style(style::danger_button)
+                    .on_press(ImageFileMessage::Delete)
+            ]
+            .spacing(10),
+        )
+        .on_enter(ImageFileMessage::DragOver)
         .into()
     }
 }
+
+/// Decodes the images one after another on a background thread, streaming
+/// what was found out about each.
+pub fn inspect(keys: Vec<ImageKey>) -> impl Stream<Item = (ImageKey, Result<ImageInfo, String>)> {
+    let (sender, receiver) = mpsc::unbounded();
+    thread::spawn(move || {
+        for key in keys {
+            let info = inspect_image(&key.path);
+            if sender.unbounded_send((key, info)).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+fn inspect_image(path: &Path) -> Result<ImageInfo, String> {
+    let file_size = fs::metadata(path).map_err(|e| e.to_string())?.len();
+    let reader = ::image::ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| e.to_string())?;
+    let format = match reader.format() {
+        Some(format) => format!("{format:?}").to_uppercase(),
+        None => return Err("unknown image format".to_string()),
+    };
+    let decoded = reader.decode().map_err(|e| e.to_string())?;
+    let thumbnail = decoded
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .into_rgba8();
+    Ok(ImageInfo {
+        thumbnail: image::Handle::from_rgba(
+            thumbnail.width(),
+            thumbnail.height(),
+            thumbnail.into_raw(),
+        ),
+        width: decoded.width(),
+        height: decoded.height(),
+        file_size,
+        format,
+    })
+}
+
+/// Formats a number of bytes for display.
+pub fn file_size(bytes: u64) -> String {
+    match bytes {
+        0..1_000 => format!("{bytes} B"),
+        1_000..1_000_000 => format!("{:.1} KB", bytes as f64 / 1e3),
+        _ => format!("{:.1} MB", bytes as f64 / 1e6),
+    }
+}
@@ -1,27 +1,78 @@
-use std::{cell::RefCell, path::PathBuf, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt, mem,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use iced::{
     Element,
     Length::FillPortion,
-    widget::{button, column, container, radio, row, scrollable, text, text_input, toggler},
+    Task,
+    widget::{
+        button, column, container, mouse_area, pick_list, radio, row, scrollable, text, text_input,
+        toggler,
+    },
+};
+pub use image_file::file_size;
+use image_file::{ImageFile, ImageFileMessage, ImageInfo, ImageKey, inspect};
+use quickstitch_app::{
+    ComicInfo, DEFAULT_MAX_HEIGHT, DEFAULT_MIN_HEIGHT, DEFAULT_NAME_TEMPLATE,
+    DEFAULT_SCAN_INTERVAL, DEFAULT_SENSITIVITY, Input, OnExisting, OutputFormat, PageName, Sort,
+    StitchJob, check_name_template, is_archive, is_image, sort_by_name, today,
 };
-use image_file::{ImageFile, ImageFileMessage};
 use rfd::FileDialog;
+use serde::{Deserialize, Serialize};
 
-use super::icons::{add_file_icon, add_folder_icon, folder_icon, image_icon};
+use crate::settings::{self, Settings};
+use crate::stitcher::StitcherError;
+
+use super::icons::{
+    add_file_icon, add_folder_icon, archive_icon, delete_icon, folder_icon, image_icon, list_icon,
+};
+use super::style;
 
 mod image_file;
 
 pub struct IOSection {
     input_type: InputType,
     input_directory: Option<PathBuf>,
+    input_archive: Option<PathBuf>,
+    auto_restitch: bool,
     ignore_unloadable: bool,
-    sort_method: SortMethod,
+    sort_method: Sort,
     input_files: Vec<ImageFile>,
+    /// What's known about each input image as it was when added, kept even
+    /// after the image is removed so that adding it back unchanged is
+    /// instant.
+    image_info: HashMap<ImageKey, Result<ImageInfo, String>>,
+    /// The input image being dragged to a new position.
+    dragged_file: Option<usize>,
     output_directory: Option<PathBuf>,
-    output_format: Rc<RefCell<ImageFormat>>,
+    on_existing: OnExisting,
+    /// Shared with the limit section, whose limits depend on it. The JPEG
+    /// quality is kept in `quality` instead, as its field can be left empty.
+    output_format: Rc<RefCell<OutputFormat>>,
     quality_field: String,
     quality: Option<u8>,
+    name_template: String,
+    pack_cbz: bool,
+    series: String,
+    chapter: String,
+    title: String,
+    /// Input directories and archives, most recent first.
+    recent_inputs: Vec<PathBuf>,
+    /// The recent inputs that are still directories and archives, checked
+    /// whenever the list changes rather than on every render.
+    recent_directories: Vec<RecentPath>,
+    recent_archives: Vec<RecentPath>,
+    /// Output directories, most recent first.
+    recent_outputs: Vec<PathBuf>,
+    /// Whether files are being dragged over the window.
+    drop_hovered: bool,
+    /// Why the last dropped file couldn't be used.
+    drop_notice: Option<String>,
 }
 
 impl Default for IOSection {
@@ -29,47 +80,69 @@ impl Default for IOSection {
         Self {
             input_type: InputType::default(),
             input_directory: None,
+            input_archive: None,
+            auto_restitch: false,
             ignore_unloadable: true,
-            sort_method: SortMethod::default(),
+            sort_method: Sort::default(),
             input_files: vec![],
+            image_info: HashMap::new(),
+            dragged_file: None,
             output_directory: None,
-            output_format: Rc::default(),
+            on_existing: OnExisting::default(),
+            output_format: Rc::new(RefCell::new(OutputFormat::Jpg(100))),
             quality_field: "100".to_string(),
             quality: Some(100),
+            name_template: DEFAULT_NAME_TEMPLATE.to_string(),
+            pack_cbz: false,
+            series: String::new(),
+            chapter: String::new(),
+            title: String::new(),
+            recent_inputs: vec![],
+            recent_directories: vec![],
+            recent_archives: vec![],
+            recent_outputs: vec![],
+            drop_hovered: false,
+            drop_notice: None,
         }
     }
 }
 
-#[derive(Default, Clone, Debug, Copy)]
+#[derive(Default, Clone, Debug, PartialEq, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum InputType {
     #[default]
     Directory,
+    Archive,
     Images,
 }
 
-#[derive(Default, PartialEq, Eq, Copy, Clone, Debug)]
-pub enum SortMethod {
-    #[default]
-    Natural,
-    Logical,
-}
+/// A recently used directory or archive, as listed in the recent dropdowns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentPath(PathBuf);
 
-#[derive(Default, Clone, Debug, PartialEq, Copy)]
-pub enum ImageFormat {
-    #[default]
-    JPEG,
-    WebP,
-    PNG,
+impl fmt::Display for RecentPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.display())
+    }
 }
 
-impl ImageFormat {
-    pub fn limit(&self) -> usize {
-        match self {
-            ImageFormat::JPEG => 65_535,
-            ImageFormat::WebP => 16_383,
-            ImageFormat::PNG => u32::MAX as usize,
-        }
+/// Adds a dropdown of recently used paths below a field, if there are any.
+fn recent_field<'a>(
+    field: Element<'a, IOSectionMessage>,
+    recent: Vec<RecentPath>,
+    use_recent: fn(RecentPath) -> IOSectionMessage,
+) -> Element<'a, IOSectionMessage> {
+    if recent.is_empty() {
+        return field;
     }
+    column![
+        field,
+        pick_list(recent, None::<RecentPath>, use_recent)
+            .placeholder("Recent")
+            .text_size(16)
+    ]
+    .spacing(10)
+    .into()
 }
 
 #[derive(Debug, Clone)]
@@ -77,26 +150,106 @@ pub enum IOSectionMessage {
     ImageFileMessage(usize, ImageFileMessage),
     SetInputType(InputType),
     SetInputDirectory,
+    SetInputArchive,
+    SetAutoRestitch(bool),
     SetIgnoreUnloadable(bool),
     SetOutputDirectory,
+    SetOnExisting(OnExisting),
     AddImage,
-    SetOutputFormat(ImageFormat),
-    SetSortMethod(SortMethod),
+    StopDrag,
+    SortImages,
+    ClearImages,
+    SetOutputFormat(OutputFormat),
+    SetSortMethod(Sort),
     SetQualityField(String),
+    SetNameTemplate(String),
+    SetPackCbz(bool),
+    SetSeries(String),
+    SetChapter(String),
+    SetTitle(String),
+    FileHovered,
+    FilesHoveredLeft,
+    FileDropped(PathBuf),
+    UseRecentInput(RecentPath),
+    UseRecentOutput(RecentPath),
+    ImageInspected(ImageKey, Result<ImageInfo, String>),
 }
 
 impl IOSection {
-    pub fn input_type(&self) -> InputType {
-        self.input_type
+    pub fn new(settings: &Settings) -> Self {
+        let mut section = Self::default();
+        section.apply(settings);
+        section
+    }
+    /// Takes on the given settings, keeping the chosen inputs.
+    pub fn apply(&mut self, settings: &Settings) {
+        self.input_type = settings.input_type;
+        self.sort_method = settings.sort;
+        self.ignore_unloadable = settings.ignore_unloadable;
+        self.auto_restitch = settings.auto_restitch;
+        self.output_directory = settings.output_directory.clone();
+        self.on_existing = settings.on_existing;
+        *self.output_format.borrow_mut() = settings.format;
+        self.quality = settings.quality;
+        self.quality_field = settings
+            .quality
+            .map(|quality| quality.to_string())
+            .unwrap_or_default();
+        self.name_template = settings.name_template.clone();
+        self.pack_cbz = settings.pack_cbz;
+        self.recent_inputs = settings.recent_inputs.clone();
+        self.recent_outputs = settings.recent_outputs.clone();
+        self.check_recent_inputs();
     }
-    pub fn input_directory(&self) -> Option<PathBuf> {
-        self.input_directory.clone()
+    pub fn save(&self, settings: &mut Settings) {
+        settings.input_type = self.input_type;
+        settings.sort = self.sort_method;
+        settings.ignore_unloadable = self.ignore_unloadable;
+        settings.auto_restitch = self.auto_restitch;
+        settings.output_directory = self.output_directory.clone();
+        settings.on_existing = self.on_existing;
+        settings.format = *self.output_format.borrow();
+        settings.quality = self.quality;
+        settings.name_template = self.name_template.clone();
+        settings.pack_cbz = self.pack_cbz;
+        settings.recent_inputs = self.recent_inputs.clone();
+        settings.recent_outputs = self.recent_outputs.clone();
     }
-    pub fn sort_method(&self) -> SortMethod {
-        self.sort_method
+    fn check_recent_inputs(&mut self) {
+        let recent = |keep: fn(&Path) -> bool| {
+            self.recent_inputs
+                .iter()
+                .filter(|path| keep(path))
+                .cloned()
+                .map(RecentPath)
+                .collect()
+        };
+        self.recent_directories = recent(|path| path.is_dir());
+        self.recent_archives = recent(|path| path.is_file() && is_archive(path));
+    }
+    fn set_input_directory(&mut self, dir: PathBuf) {
+        settings::remember(&mut self.recent_inputs, &dir);
+        self.check_recent_inputs();
+        self.input_directory = Some(dir);
+    }
+    fn set_input_archive(&mut self, archive: PathBuf) {
+        settings::remember(&mut self.recent_inputs, &archive);
+        self.check_recent_inputs();
+        self.input_archive = Some(archive);
     }
-    pub fn ignore_unlodable(&self) -> bool {
-        self.ignore_unloadable
+    fn set_output_directory(&mut self, dir: PathBuf) {
+        settings::remember(&mut self.recent_outputs, &dir);
+        self.output_directory = Some(dir);
+    }
+    /// The input directory to watch for changes, if auto-restitch is on and
+    /// the existing files policy lets each rebuild replace the last.
+    pub fn watched_directory(&self) -> Option<PathBuf> {
+        match self.input_type {
+            InputType::Directory if self.auto_restitch && self.on_existing.allows_rebuilds() => {
+                self.input_directory.clone()
+            }
+            _ => None,
+        }
     }
     pub fn input_files(&self) -> Vec<PathBuf> {
         self.input_files.iter().map(|file| file.path()).collect()
@@ -104,43 +257,157 @@ impl IOSection {
     pub fn output_directory(&self) -> Option<PathBuf> {
         self.output_directory.clone()
     }
-    pub fn compression_quality(&self) -> Option<u8> {
-        self.quality
+    /// A job for the chosen inputs and output, with the default limits and
+    /// settings for the other sections to fill in.
+    pub fn stitch_job(&self) -> Result<StitchJob, StitcherError> {
+        let input = match self.input_type {
+            InputType::Directory => Input::Directory(
+                self.input_directory
+                    .clone()
+                    .ok_or(StitcherError::NoInputDirectory)?,
+            ),
+            InputType::Archive => Input::Archive(
+                self.input_archive
+                    .clone()
+                    .ok_or(StitcherError::NoInputArchive)?,
+            ),
+            InputType::Images => Input::Images(self.input_files()),
+        };
+        let output = self
+            .output_directory
+            .clone()
+            .ok_or(StitcherError::NoOutputDirectory)?;
+        let format = match *self.output_format.borrow() {
+            OutputFormat::Jpg(_) => {
+                OutputFormat::Jpg(self.quality.ok_or(StitcherError::EmptyQuality)?)
+            }
+            OutputFormat::Jpeg(_) => {
+                OutputFormat::Jpeg(self.quality.ok_or(StitcherError::EmptyQuality)?)
+            }
+            format => format,
+        };
+        Ok(StitchJob {
+            input,
+            sort: self.sort_method,
+            ignore_unloadable: self.ignore_unloadable,
+            output,
+            on_existing: self.on_existing,
+            format,
+            comic_info: self.comic_info(),
+            name_template: self.name_template.clone(),
+            width: None,
+            max_height: DEFAULT_MAX_HEIGHT,
+            min_height: DEFAULT_MIN_HEIGHT,
+            scan_interval: DEFAULT_SCAN_INTERVAL,
+            sensitivity: DEFAULT_SENSITIVITY,
+            debug: false,
+        })
+    }
+    /// The name the first page would get, using the chosen inputs where they
+    /// are known.
+    fn name_example(&self) -> Result<String, String> {
+        check_name_template(&self.name_template)?;
+        let input = match self.input_type {
+            InputType::Directory => self.input_directory.clone().map(Input::Directory),
+            InputType::Archive => self.input_archive.clone().map(Input::Archive),
+            InputType::Images => Some(Input::Images(vec![])),
+        };
+        let first = match self.input_type {
+            InputType::Images => self.input_files.first().map(|file| file.path()),
+            _ => None,
+        };
+        PageName {
+            page: 1,
+            pages: 12,
+            chapter: input.map_or("chapter".to_string(), |input| input.name()),
+            first: first
+                .as_deref()
+                .and_then(Path::file_stem)
+                .map_or("001".to_string(), |stem| {
+                    stem.to_string_lossy().into_owned()
+                }),
+            date: today(),
+            ext: self.output_format.borrow().extension().to_string(),
+        }
+        .render(&self.name_template)
     }
-    pub fn output_format(&self) -> Rc<RefCell<ImageFormat>> {
+    pub fn output_format(&self) -> Rc<RefCell<OutputFormat>> {
         self.output_format.clone()
     }
+    fn comic_info(&self) -> Option<ComicInfo> {
+        let field = |value: &String| (!value.trim().is_empty()).then(|| value.trim().to_string());
+        self.pack_cbz.then(|| ComicInfo {
+            series: field(&self.series),
+            number: field(&self.chapter),
+            title: field(&self.title),
+        })
+    }
     pub fn view(&self) -> Element<IOSectionMessage> {
-        let select_dir_field =
-            |set_dir_message, dir: &Option<PathBuf>| -> Element<IOSectionMessage> {
-                match &dir {
-                    Some(dir) => column![
-                        scrollable(text(dir.display().to_string()).size(20))
-                            .horizontal()
-                            .spacing(5),
-                        button(
-                            row![
-                                add_folder_icon().size(20),
-                                text("Change Directory").size(20)
-                            ]
-                            .spacing(10)
-                        )
-                        .on_press(set_dir_message)
-                        .style(button::danger)
-                    ]
-                    .spacing(10)
-                    .into(),
-                    None => button(
+        let select_dir_field = |set_dir_message,
+                                dir: &Option<PathBuf>,
+                                recent: Vec<RecentPath>,
+                                use_recent: fn(RecentPath) -> IOSectionMessage|
+         -> Element<IOSectionMessage> {
+            let field: Element<_> = match &dir {
+                Some(dir) => column![
+                    scrollable(text(dir.display().to_string()).size(20))
+                        .horizontal()
+                        .spacing(5),
+                    button(
                         row![
                             add_folder_icon().size(20),
-                            text("Select Directory").size(20)
+                            text("Change Directory").size(20)
                         ]
-                        .spacing(10),
+                        .spacing(10)
                     )
                     .on_press(set_dir_message)
-                    .into(),
-                }
+                    .style(style::danger_button)
+                ]
+                .spacing(10)
+                .into(),
+                None => button(
+                    row![
+                        add_folder_icon().size(20),
+                        text("Select Directory").size(20)
+                    ]
+                    .spacing(10),
+                )
+                .on_press(set_dir_message)
+                .into(),
             };
+            recent_field(field, recent, use_recent)
+        };
+
+        let image_sorting = || {
+            row![
+                column![
+                    text("Image Sorting").size(20),
+                    text("Choose how the input images are sorted")
+                        .size(16)
+                        .style(text::secondary)
+                ]
+                .width(FillPortion(1)),
+                column![
+                    radio(
+                        "Natural - [\"8\", \"9\", \"10\", \"11\"]",
+                        Sort::Natural,
+                        Some(self.sort_method),
+                        IOSectionMessage::SetSortMethod
+                    )
+                    .size(20),
+                    radio(
+                        "Logical - [\"10\", \"11\", \"8\", \"9\"]",
+                        Sort::Logical,
+                        Some(self.sort_method),
+                        IOSectionMessage::SetSortMethod
+                    )
+                    .size(20)
+                ]
+                .spacing(10)
+                .width(FillPortion(1))
+            ]
+            .spacing(20)
+        };
 
         let images: Element<_> = match self.input_type {
             InputType::Directory => column![
@@ -154,79 +421,154 @@ impl IOSection {
                     .width(FillPortion(1)),
                     container(select_dir_field(
                         IOSectionMessage::SetInputDirectory,
-                        &self.input_directory
+                        &self.input_directory,
+                        self.recent_directories.clone(),
+                        IOSectionMessage::UseRecentInput,
                     ))
                     .width(FillPortion(1)),
                 ]
                 .spacing(20),
                 row![
                     column![
-                        text("Image Sorting").size(20),
-                        text("Choose how the input images are sorted")
-                            .size(16)
-                            .style(text::secondary)
+                        text("Auto-restitch").size(20),
+                        text(match self.on_existing.allows_rebuilds() {
+                            true => "Stitch again whenever images in the input directory change",
+                            false =>
+                                "Needs Overwrite or Clean for existing files, since every \
+                                      stitch would otherwise stop or make a new folder",
+                        })
+                        .size(16)
+                        .style(text::secondary)
                     ]
                     .width(FillPortion(1)),
-                    column![
-                        radio(
-                            "Natural - [\"10\", \"11\", \"8\", \"9\"]",
-                            SortMethod::Natural,
-                            Some(self.sort_method),
-                            IOSectionMessage::SetSortMethod
-                        )
-                        .size(20),
-                        radio(
-                            "Logical - [\"8\", \"9\", \"10\", \"11\"]",
-                            SortMethod::Logical,
-                            Some(self.sort_method),
-                            IOSectionMessage::SetSortMethod
+                    toggler(self.auto_restitch && self.on_existing.allows_rebuilds())
+                        .on_toggle_maybe(
+                            self.on_existing
+                                .allows_rebuilds()
+                                .then_some(IOSectionMessage::SetAutoRestitch)
                         )
                         .size(20)
-                    ]
-                    .spacing(10)
-                    .width(FillPortion(1))
+                        .width(FillPortion(1))
                 ]
-                .spacing(20)
+                .spacing(20),
+                image_sorting()
             ]
             .spacing(20)
             .into(),
-            InputType::Images => row![
-                column![
-                    text("Input Images").size(20),
-                    text("Choose which images will be used")
-                        .size(16)
-                        .style(text::secondary)
-                ]
-                .width(FillPortion(1)),
-                if !self.input_files.is_empty() {
-                    column![
-                        column(self.input_files.iter().enumerate().map(|(i, file)| {
-                            file.view()
-                                .map(move |a| IOSectionMessage::ImageFileMessage(i, a))
-                        }))
-                        .spacing(10),
-                        button(
-                            row![add_file_icon().size(20), text("Add Image").size(20)].spacing(10)
-                        )
-                        .on_press(IOSectionMessage::AddImage)
-                    ]
-                    .spacing(10)
-                    .width(FillPortion(1))
-                } else {
+            InputType::Archive => column![
+                row![
                     column![
-                        button(row![add_file_icon().size(20), text("Add Image").size(20)])
-                            .on_press(IOSectionMessage::AddImage)
+                        text("Input Archive").size(20),
+                        text("CBZ or ZIP file from which the images will be taken")
+                            .size(16)
+                            .style(text::secondary)
                     ]
-                    .width(FillPortion(1))
-                }
+                    .width(FillPortion(1)),
+                    container(recent_field(
+                        match &self.input_archive {
+                            Some(archive) => column![
+                                scrollable(text(archive.display().to_string()).size(20))
+                                    .horizontal()
+                                    .spacing(5),
+                                button(
+                                    row![archive_icon().size(20), text("Change Archive").size(20)]
+                                        .spacing(10)
+                                )
+                                .on_press(IOSectionMessage::SetInputArchive)
+                                .style(style::danger_button)
+                            ]
+                            .spacing(10)
+                            .into(),
+                            None => column![
+                                button(
+                                    row![archive_icon().size(20), text("Select Archive").size(20)]
+                                        .spacing(10)
+                                )
+                                .on_press(IOSectionMessage::SetInputArchive)
+                            ]
+                            .into(),
+                        },
+                        self.recent_archives.clone(),
+                        IOSectionMessage::UseRecentInput,
+                    ))
+                    .width(FillPortion(1)),
+                ]
+                .spacing(20),
+                image_sorting()
             ]
             .spacing(20)
             .into(),
+            InputType::Images => {
+                let last = self.input_files.len().saturating_sub(1);
+                let typical_width = self.typical_width();
+                let mut images = column![
+                    row![
+                        column![
+                            text("Input Images").size(20),
+                            text("Choose which images will be used, top to bottom")
+                                .size(16)
+                                .style(text::secondary)
+                        ]
+                        .width(FillPortion(1)),
+                        container(
+                            button(
+                                row![add_file_icon().size(20), text("Add Images").size(20)]
+                                    .spacing(10)
+                            )
+                            .on_press(IOSectionMessage::AddImage)
+                        )
+                        .width(FillPortion(1))
+                    ]
+                    .spacing(20)
+                ]
+                .spacing(10);
+                if !self.input_files.is_empty() {
+                    images = images
+                        .push(
+                            mouse_area(
+                                column(self.input_files.iter().enumerate().map(|(i, file)| {
+                                    file.view(
+                                        self.image_info.get(file.key()),
+                                        typical_width,
+                                        i == 0,
+                                        i == last,
+                                        self.dragged_file == Some(i),
+                                    )
+                                    .map(move |a| IOSectionMessage::ImageFileMessage(i, a))
+                                }))
+                                .spacing(10),
+                            )
+                            .on_release(IOSectionMessage::StopDrag)
+                            .on_exit(IOSectionMessage::StopDrag),
+                        )
+                        .push(
+                            row![
+                                button(
+                                    row![list_icon().size(20), text("Sort List Now").size(20)]
+                                        .spacing(10)
+                                )
+                                .on_press(IOSectionMessage::SortImages)
+                                .style(button::secondary),
+                                button(
+                                    row![delete_icon().size(20), text("Clear All").size(20)]
+                                        .spacing(10)
+                                )
+                                .on_press(IOSectionMessage::ClearImages)
+                                .style(style::danger_button),
+                            ]
+                            .spacing(10),
+                        );
+                }
+                column![images, image_sorting()].spacing(20).into()
+            }
         };
 
-        let image_format_button = |name, filetype, curr_filetype: Rc<RefCell<ImageFormat>>| {
-            button(text(name).size(20))
-                .style(if filetype == *curr_filetype.borrow() {
+        // JPEG is chosen as `Jpg(100)`, the quality comes from its own field.
+        let image_format_button = |filetype: OutputFormat| {
+            let selected =
+                mem::discriminant(&filetype) == mem::discriminant(&*self.output_format.borrow());
+            button(text(filetype.to_string()).size(20))
+                .style(if selected {
                     button::primary
                 } else {
                     button::text
@@ -234,6 +576,49 @@ impl IOSection {
                 .on_press(IOSectionMessage::SetOutputFormat(filetype))
         };
 
+        let existing_files = row![
+            column![
+                text("Existing Files").size(20),
+                text("What to do when the output directory already has files in it")
+                    .size(16)
+                    .style(text::secondary)
+            ]
+            .width(FillPortion(1)),
+            column![
+                radio(
+                    "Overwrite - Replace pages with the same names, after asking",
+                    OnExisting::Overwrite,
+                    Some(self.on_existing),
+                    IOSectionMessage::SetOnExisting
+                )
+                .size(20),
+                radio(
+                    "Clean - Remove the pages of the last run first",
+                    OnExisting::Clean,
+                    Some(self.on_existing),
+                    IOSectionMessage::SetOnExisting
+                )
+                .size(20),
+                radio(
+                    "New Folder - Save to a numbered folder next to it",
+                    OnExisting::NewDir,
+                    Some(self.on_existing),
+                    IOSectionMessage::SetOnExisting
+                )
+                .size(20),
+                radio(
+                    "Stop - Don't write anything",
+                    OnExisting::Error,
+                    Some(self.on_existing),
+                    IOSectionMessage::SetOnExisting
+                )
+                .size(20)
+            ]
+            .spacing(10)
+            .width(FillPortion(1))
+        ]
+        .spacing(20);
+
         let mut output_format = column![
             row![
                 column![
@@ -244,9 +629,9 @@ impl IOSection {
                 ]
                 .width(FillPortion(1)),
                 row![
-                    image_format_button("JPEG", ImageFormat::JPEG, self.output_format.clone()),
-                    image_format_button("WebP", ImageFormat::WebP, self.output_format.clone()),
-                    image_format_button("PNG", ImageFormat::PNG, self.output_format.clone())
+                    image_format_button(OutputFormat::Jpg(100)),
+                    image_format_button(OutputFormat::Webp),
+                    image_format_button(OutputFormat::Png)
                 ]
                 .spacing(10)
                 .width(FillPortion(1))
@@ -255,7 +640,7 @@ impl IOSection {
         ]
         .spacing(20);
 
-        if *self.output_format.borrow() == ImageFormat::JPEG {
+        if let OutputFormat::Jpg(_) | OutputFormat::Jpeg(_) = *self.output_format.borrow() {
             output_format = output_format.push(
                 row![
                     column![
@@ -273,9 +658,93 @@ impl IOSection {
             );
         }
 
+        let name_example = match self.name_example() {
+            Ok(name) => text(format!("e.g. {name}")).style(text::secondary),
+            Err(reason) => text(format!("The template {reason}")).style(style::danger_text),
+        };
+        let file_names = row![
+            column![
+                text("File Names").size(20),
+                text(
+                    "{page} and {pages} can be padded like {page:03}. Also {chapter}, {first}, \
+                     {date} and {ext}"
+                )
+                .size(16)
+                .style(text::secondary)
+            ]
+            .width(FillPortion(1)),
+            column![
+                text_input(DEFAULT_NAME_TEMPLATE, &self.name_template)
+                    .on_input(IOSectionMessage::SetNameTemplate),
+                name_example.size(16),
+            ]
+            .spacing(5)
+            .width(FillPortion(1)),
+        ]
+        .spacing(20);
+
+        let mut packing = column![
+            row![
+                column![
+                    text("Pack as CBZ").size(20),
+                    text("Write a single .cbz with a ComicInfo.xml instead of loose images")
+                        .size(16)
+                        .style(text::secondary)
+                ]
+                .width(FillPortion(1)),
+                toggler(self.pack_cbz)
+                    .on_toggle(IOSectionMessage::SetPackCbz)
+                    .size(20)
+                    .width(FillPortion(1))
+            ]
+            .spacing(20)
+        ]
+        .spacing(20);
+
+        if self.pack_cbz {
+            let metadata_field = |title, hint, value, on_input: fn(String) -> IOSectionMessage| {
+                row![
+                    text(title).size(20).width(FillPortion(1)),
+                    text_input(hint, value)
+                        .width(FillPortion(1))
+                        .on_input(on_input),
+                ]
+                .spacing(20)
+            };
+            packing = packing
+                .push(metadata_field(
+                    "Series",
+                    "Optional",
+                    &self.series,
+                    IOSectionMessage::SetSeries,
+                ))
+                .push(metadata_field(
+                    "Chapter",
+                    "Optional",
+                    &self.chapter,
+                    IOSectionMessage::SetChapter,
+                ))
+                .push(metadata_field(
+                    "Title",
+                    "Optional",
+                    &self.title,
+                    IOSectionMessage::SetTitle,
+                ));
+        }
+
+        let drop_status = match (&self.drop_notice, self.drop_hovered) {
+            (_, true) => Some(
+                text("Drop folders, archives or images to use them as input")
+                    .size(16)
+                    .style(text::primary),
+            ),
+            (Some(notice), false) => Some(text(notice).size(16).style(style::danger_text)),
+            (None, false) => None,
+        };
+
         // Final UI
 
-        column![
+        let fields = column![
             row![
                 column![
                     text("Input Type").size(20),
@@ -289,13 +758,19 @@ impl IOSection {
                         .on_press(IOSectionMessage::SetInputType(InputType::Directory))
                         .style(match self.input_type {
                             InputType::Directory => button::primary,
-                            InputType::Images => button::text,
+                            _ => button::text,
+                        }),
+                    button(row![archive_icon().size(20), text("Archive").size(20)].spacing(10))
+                        .on_press(IOSectionMessage::SetInputType(InputType::Archive))
+                        .style(match self.input_type {
+                            InputType::Archive => button::primary,
+                            _ => button::text,
                         }),
                     button(row![image_icon().size(20), text("Images").size(20)].spacing(10))
                         .on_press(IOSectionMessage::SetInputType(InputType::Images))
                         .style(match self.input_type {
-                            InputType::Directory => button::text,
                             InputType::Images => button::primary,
+                            _ => button::text,
                         })
                 ]
                 .spacing(10)
@@ -327,46 +802,98 @@ impl IOSection {
                 .width(FillPortion(1)),
                 container(select_dir_field(
                     IOSectionMessage::SetOutputDirectory,
-                    &self.output_directory
+                    &self.output_directory,
+                    self.recent_outputs
+                        .iter()
+                        .cloned()
+                        .map(RecentPath)
+                        .collect(),
+                    IOSectionMessage::UseRecentOutput,
                 ))
                 .width(FillPortion(1))
             ]
             .spacing(20),
+            existing_files,
             output_format,
+            file_names,
+            packing,
         ]
-        .spacing(20)
-        .into()
+        .spacing(20);
+        match drop_status {
+            Some(status) => column![status, fields].spacing(20).into(),
+            None => fields.into(),
+        }
     }
-    pub fn update(&mut self, message: IOSectionMessage) {
+    pub fn update(&mut self, message: IOSectionMessage) -> Task<IOSectionMessage> {
         match message {
             IOSectionMessage::ImageFileMessage(i, message) => {
+                let files = &mut self.input_files;
                 match message {
-                    ImageFileMessage::Delete => self.input_files.remove(i),
-                };
+                    ImageFileMessage::Delete => {
+                        files.remove(i);
+                    }
+                    ImageFileMessage::MoveUp => files.swap(i, i - 1),
+                    ImageFileMessage::MoveDown => files.swap(i, i + 1),
+                    ImageFileMessage::MoveToTop => files[..=i].rotate_right(1),
+                    ImageFileMessage::MoveToBottom => files[i..].rotate_left(1),
+                    ImageFileMessage::StartDrag => self.dragged_file = Some(i),
+                    ImageFileMessage::DragOver => {
+                        // The dragged image follows the cursor, so the list
+                        // always shows where it will end up.
+                        if let Some(dragged) = self.dragged_file
+                            && dragged != i
+                        {
+                            let file = files.remove(dragged);
+                            files.insert(i, file);
+                            self.dragged_file = Some(i);
+                        }
+                    }
+                }
             }
             IOSectionMessage::SetInputDirectory => {
                 if let Some(dir) = FileDialog::new().pick_folder() {
-                    self.input_directory = Some(dir);
+                    self.set_input_directory(dir);
+                }
+            }
+            IOSectionMessage::SetInputArchive => {
+                if let Some(archive) = FileDialog::new()
+                    .add_filter("Archive (cbz, zip)", &["cbz", "zip"])
+                    .pick_file()
+                {
+                    self.set_input_archive(archive);
                 }
             }
             IOSectionMessage::SetOutputDirectory => {
                 if let Some(dir) = FileDialog::new().pick_folder() {
-                    self.output_directory = Some(dir)
+                    self.set_output_directory(dir);
                 }
             }
             IOSectionMessage::SetInputType(input_type) => self.input_type = input_type,
             IOSectionMessage::AddImage => {
-                if let Some(file) = FileDialog::new()
+                if let Some(mut files) = FileDialog::new()
                     .add_filter("Image (webp, png, jpeg)", &["webp", "png", "jpeg", "jpg"])
-                    .pick_file()
+                    .pick_files()
                 {
-                    self.input_files.push(ImageFile::with_path(file));
+                    sort_by_name(&mut files, self.sort_method);
+                    return self.add_images(files);
                 }
             }
+            IOSectionMessage::StopDrag => self.dragged_file = None,
+            IOSectionMessage::SortImages => {
+                let mut files = self.input_files();
+                sort_by_name(&mut files, self.sort_method);
+                self.input_files.clear();
+                return self.add_images(files);
+            }
+            IOSectionMessage::ClearImages => self.input_files.clear(),
             IOSectionMessage::SetOutputFormat(output_format) => {
                 *self.output_format.borrow_mut() = output_format
             }
             IOSectionMessage::SetSortMethod(sort_method) => self.sort_method = sort_method,
+            IOSectionMessage::SetOnExisting(on_existing) => self.on_existing = on_existing,
+            IOSectionMessage::SetNameTemplate(name_template) => {
+                self.name_template = name_template;
+            }
             IOSectionMessage::SetQualityField(quality_field) => {
                 if let Ok(num) = quality_field.parse::<u8>()
                     && num <= 100
@@ -379,9 +906,89 @@ impl IOSection {
                     self.quality = None;
                 }
             }
+            IOSectionMessage::SetAutoRestitch(auto_restitch) => self.auto_restitch = auto_restitch,
             IOSectionMessage::SetIgnoreUnloadable(ignore_unloadable) => {
                 self.ignore_unloadable = ignore_unloadable;
             }
+            IOSectionMessage::SetPackCbz(pack_cbz) => self.pack_cbz = pack_cbz,
+            IOSectionMessage::SetSeries(series) => self.series = series,
+            IOSectionMessage::SetChapter(chapter) => self.chapter = chapter,
+            IOSectionMessage::SetTitle(title) => self.title = title,
+            IOSectionMessage::FileHovered => {
+                self.drop_hovered = true;
+                self.drop_notice = None;
+            }
+            IOSectionMessage::FilesHoveredLeft => self.drop_hovered = false,
+            IOSectionMessage::FileDropped(path) => {
+                self.drop_hovered = false;
+                match self.drop_file(path) {
+                    Ok(task) => return task,
+                    Err(notice) => self.drop_notice = Some(notice),
+                }
+            }
+            IOSectionMessage::UseRecentInput(RecentPath(path)) => {
+                if path.is_dir() {
+                    self.set_input_directory(path);
+                } else {
+                    self.set_input_archive(path);
+                }
+            }
+            IOSectionMessage::UseRecentOutput(RecentPath(dir)) => self.set_output_directory(dir),
+            IOSectionMessage::ImageInspected(key, info) => {
+                self.image_info.insert(key, info);
+            }
+        }
+        Task::none()
+    }
+    /// Appends images to the input list, inspecting the ones that haven't
+    /// been seen before, or have changed since, in the background.
+    fn add_images(&mut self, paths: Vec<PathBuf>) -> Task<IOSectionMessage> {
+        let keys: Vec<ImageKey> = paths.into_iter().map(ImageKey::new).collect();
+        let new: Vec<ImageKey> = keys
+            .iter()
+            .filter(|key| !self.image_info.contains_key(*key))
+            .cloned()
+            .collect();
+        self.input_files
+            .extend(keys.into_iter().map(ImageFile::with_key));
+        if new.is_empty() {
+            return Task::none();
+        }
+        Task::run(inspect(new), |(key, info)| {
+            IOSectionMessage::ImageInspected(key, info)
+        })
+    }
+    /// The median width of the input images that could be loaded.
+    fn typical_width(&self) -> Option<u32> {
+        let mut widths: Vec<u32> = self
+            .input_files
+            .iter()
+            .filter_map(|file| self.image_info.get(file.key())?.as_ref().ok())
+            .map(|info| info.width)
+            .collect();
+        widths.sort_unstable();
+        widths.get(widths.len() / 2).copied()
+    }
+    /// Uses a file dropped onto the window as input, depending on what it is.
+    ///
+    /// The window isn't told where files are dropped, so dropped folders
+    /// can't be told apart by field and always become the input.
+    fn drop_file(&mut self, path: PathBuf) -> Result<Task<IOSectionMessage>, String> {
+        let name = path.file_name().unwrap_or_default().display().to_string();
+        if path.is_dir() {
+            self.set_input_directory(path);
+            self.input_type = InputType::Directory;
+        } else if is_archive(&path) {
+            self.set_input_archive(path);
+            self.input_type = InputType::Archive;
+        } else if path.is_file() && is_image(&path) {
+            self.input_type = InputType::Images;
+            return Ok(self.add_images(vec![path]));
+        } else {
+            return Err(format!(
+                "{name} is not a folder, CBZ/ZIP archive or supported image"
+            ));
         }
+        Ok(Task::none())
     }
 }
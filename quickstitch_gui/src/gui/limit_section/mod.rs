@@ -6,12 +6,14 @@ use iced::{
     widget::{column, radio, row, text},
 };
 use pixel_field::{PixelField, PixelFieldMessage};
+use quickstitch_app::{OutputFormat, StitchJob};
+use serde::{Deserialize, Serialize};
 
-use super::io_section::ImageFormat;
+use crate::settings::Settings;
+use crate::stitcher::StitcherError;
 
 mod pixel_field;
 
-#[derive(Default)]
 pub struct LimitSection {
     width_type: WidthType,
     fixed_width: PixelField,
@@ -19,7 +21,8 @@ pub struct LimitSection {
     min_height: PixelField,
 }
 
-#[derive(Default, PartialEq, Eq, Copy, Clone, Debug)]
+#[derive(Default, PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum WidthType {
     Auto,
     #[default]
@@ -35,41 +38,68 @@ pub enum LimitSectionMessage {
 }
 
 impl LimitSection {
-    pub fn width_type(&self) -> WidthType {
-        self.width_type
-    }
-    pub fn fixed_width(&self) -> Option<u32> {
+    fn fixed_width(&self) -> Option<u32> {
         self.fixed_width.number().map(|num| num as u32)
     }
-    pub fn max_height(&self) -> Option<usize> {
+    fn max_height(&self) -> Option<usize> {
         self.max_height.number()
     }
-    pub fn min_height(&self) -> Option<usize> {
+    fn min_height(&self) -> Option<usize> {
         self.min_height.number()
     }
-    pub fn new(output_format: Rc<RefCell<ImageFormat>>) -> Self {
+    /// Sets the job's width and page heights.
+    pub fn configure(&self, job: &mut StitchJob) -> Result<(), StitcherError> {
+        job.width = match self.width_type {
+            WidthType::Auto => None,
+            WidthType::Fixed => Some(
+                self.fixed_width()
+                    .ok_or(StitcherError::EmptyOutputImageWidth)?,
+            ),
+        };
+        job.max_height = self
+            .max_height()
+            .ok_or(StitcherError::EmptyMaxOutputHeight)?;
+        job.min_height = self
+            .min_height()
+            .ok_or(StitcherError::EmptyMinOutputHeight)?;
+        Ok(())
+    }
+    pub fn new(output_format: Rc<RefCell<OutputFormat>>, settings: &Settings) -> Self {
         Self {
+            width_type: settings.width_type,
             fixed_width: PixelField::new(
                 "Output Image Width",
                 "e.g. 800",
-                Some(800),
+                settings.width.map(|width| width as usize),
                 output_format.clone(),
             ),
             max_height: PixelField::new(
                 "Max Output Height",
                 "e.g. 15000",
-                Some(15000),
+                settings.max_height,
                 output_format.clone(),
             ),
             min_height: PixelField::new(
                 "Min Output Height",
                 "e.g. 10000",
-                Some(10000),
+                settings.min_height,
                 output_format.clone(),
             ),
-            ..Default::default()
         }
     }
+    pub fn apply(&mut self, settings: &Settings) {
+        self.width_type = settings.width_type;
+        self.fixed_width
+            .set_number(settings.width.map(|width| width as usize));
+        self.max_height.set_number(settings.max_height);
+        self.min_height.set_number(settings.min_height);
+    }
+    pub fn save(&self, settings: &mut Settings) {
+        settings.width_type = self.width_type;
+        settings.width = self.fixed_width();
+        settings.max_height = self.max_height();
+        settings.min_height = self.min_height();
+    }
     pub fn view(&self) -> Element<LimitSectionMessage> {
         let mut width_settings = column![
             row![
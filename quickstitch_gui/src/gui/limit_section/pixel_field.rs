@@ -6,15 +6,14 @@ use iced::{
     widget::{column, row, text, text_input},
 };
 
-use crate::gui::io_section::ImageFormat;
+use quickstitch_app::OutputFormat;
 
-#[derive(Default)]
 pub struct PixelField {
     title: String,
     field: String,
     hint: String,
     number: Option<usize>,
-    output_format: Rc<RefCell<ImageFormat>>,
+    output_format: Rc<RefCell<OutputFormat>>,
 }
 
 #[derive(Debug, Clone)]
@@ -26,11 +25,15 @@ impl PixelField {
     pub fn number(&self) -> Option<usize> {
         self.number
     }
+    pub fn set_number(&mut self, number: Option<usize>) {
+        self.number = number;
+        self.field = number.map(|num| num.to_string()).unwrap_or_default();
+    }
     pub fn new<S: AsRef<str>>(
         title: S,
         hint: S,
         prefill: Option<usize>,
-        output_format: Rc<RefCell<ImageFormat>>,
+        output_format: Rc<RefCell<OutputFormat>>,
     ) -> Self {
         Self {
             title: title.as_ref().to_string(),
@@ -42,7 +45,6 @@ impl PixelField {
                 String::new()
             },
             output_format,
-            ..Default::default()
         }
     }
     pub fn view(&self) -> Element<PixelFieldMessage> {
@@ -51,7 +53,7 @@ impl PixelField {
                 text(&self.title).size(20),
                 text(format!(
                     "Max: {} pixels",
-                    self.output_format.borrow().limit()
+                    self.output_format.borrow().max_dimension()
                 ))
                 .size(16)
                 .style(text::secondary),
@@ -67,7 +69,7 @@ impl PixelField {
         match message {
             PixelFieldMessage::UpdateField(field) => {
                 if let Ok(num) = field.parse::<usize>()
-                    && num < self.output_format.borrow().limit()
+                    && num <= self.output_format.borrow().max_dimension()
                 {
                     self.field = num.to_string();
                     self.number = Some(num);
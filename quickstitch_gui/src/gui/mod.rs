@@ -1,52 +1,210 @@
+use std::{collections::HashSet, fmt, path::PathBuf, sync::Arc};
+
 use iced::{
-    Element,
+    Element, Event,
     Length::FillPortion,
-    Theme,
-    widget::{Space, button, column, horizontal_rule, row, scrollable, text},
+    Subscription, Task, Theme, event,
+    futures::Stream,
+    widget::{
+        Space, button, column, horizontal_rule, pick_list, progress_bar, row, scrollable, text,
+    },
+    window,
 };
-use icons::{folder_icon, image_icon, settings_icon};
+use icons::{folder_icon, image_icon, list_icon, settings_icon};
 use io_section::{IOSection, IOSectionMessage};
 use limit_section::{LimitSection, LimitSectionMessage};
-use quickstitch::Splitpoint;
+use preset_section::{PresetSection, PresetSectionMessage};
+use preview_section::{PreviewSection, PreviewSectionMessage};
+use queue_section::{QueueSection, QueueSectionMessage};
 use setting_section::{SettingSection, SettingSectionMessage};
+use summary_section::{SummarySection, SummarySectionMessage};
+
+use quickstitch_app::{
+    CancelToken, ExportError, OnExisting, Progress, Splitpoints, StitchError, StitchJob, Strip,
+    has_existing_output,
+};
+use rfd::{MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
 
-use crate::stitcher::stitcher;
+use crate::presets::Preset;
+use crate::settings::{self, Settings};
+use crate::stitcher::{
+    StitchProgress, StitcherError, detect, export, preview, stitch, stitch_queued, watch,
+};
 
 pub mod icons;
 pub mod io_section;
 pub mod limit_section;
+pub mod preset_section;
+pub mod preview_section;
+pub mod queue_section;
 pub mod setting_section;
+pub mod style;
+pub mod summary_section;
 
 pub struct Quickstitch {
+    preset_section: PresetSection,
     io_section: IOSection,
     limit_section: LimitSection,
     setting_section: SettingSection,
-    theme: Theme,
-    splitpoints: Option<Vec<Splitpoint>>,
+    preview_section: PreviewSection,
+    queue_section: QueueSection,
+    summary_section: SummarySection,
+    theme: ThemeChoice,
+    running_stitch: Option<RunningStitch>,
+    restitch_pending: bool,
     stitch_error: String,
+    /// Every page that failed to export in the last run.
+    export_errors: Vec<ExportError>,
+    /// What the last run tried to export, if it failed to, so that retrying
+    /// exports exactly that whatever the settings are now.
+    failed_export: Option<FailedExport>,
+    show_export_errors: bool,
+    /// Output directories the user agreed to overwrite this session, so that
+    /// re-stitching into them doesn't ask every time.
+    confirmed_outputs: HashSet<PathBuf>,
+    /// The settings as last saved, or as loaded on start.
+    saved_settings: Settings,
+    settings_error: Option<String>,
+    /// Why the input directory last failed to be watched, along with the
+    /// directory, so the error is only shown while it's still being watched.
+    watch_error: Option<(PathBuf, String)>,
+}
+
+/// A built-in theme, or whichever of light and dark the system prefers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThemeChoice {
+    System,
+    Theme(Theme),
+}
+
+impl ThemeChoice {
+    fn all() -> Vec<Self> {
+        [ThemeChoice::System]
+            .into_iter()
+            .chain(Theme::ALL.iter().cloned().map(ThemeChoice::Theme))
+            .collect()
+    }
+    /// Finds a built-in theme by name, following the system if there's none.
+    fn from_name(name: Option<&str>) -> Self {
+        Theme::ALL
+            .iter()
+            .find(|theme| Some(theme.to_string().as_str()) == name)
+            .cloned()
+            .map_or(ThemeChoice::System, ThemeChoice::Theme)
+    }
+    fn name(&self) -> Option<String> {
+        match self {
+            ThemeChoice::System => None,
+            ThemeChoice::Theme(theme) => Some(theme.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ThemeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeChoice::System => write!(f, "Follow System"),
+            ThemeChoice::Theme(theme) => write!(f, "{theme}"),
+        }
+    }
+}
+
+struct RunningStitch {
+    job: StitchJob,
+    cancel: CancelToken,
+    progress: Option<Progress>,
+    /// The strip and cuts the run exports, copied as the export starts so
+    /// that a failure can be retried with exactly them.
+    exporting: Option<(Arc<Strip>, Splitpoints)>,
+}
+
+#[derive(Clone)]
+struct FailedExport {
+    job: StitchJob,
+    strip: Arc<Strip>,
+    splitpoints: Splitpoints,
 }
 
 impl Default for Quickstitch {
     fn default() -> Self {
-        let io_section = IOSection::default();
-        let limit_section = LimitSection::new(io_section.output_format());
+        let (saved_settings, settings_error) = match settings::settings_path() {
+            Some(path) => match settings::load(&path) {
+                Ok(saved) => (saved, None),
+                Err(e) => (Settings::default(), Some(e.to_string())),
+            },
+            None => (Settings::default(), None),
+        };
+        let io_section = IOSection::new(&saved_settings);
+        let limit_section = LimitSection::new(io_section.output_format(), &saved_settings);
         Self {
+            preset_section: PresetSection::new(),
             limit_section,
             io_section,
-            setting_section: SettingSection::default(),
-            theme: Theme::Light,
-            splitpoints: None,
+            setting_section: SettingSection::new(&saved_settings),
+            preview_section: PreviewSection::default(),
+            queue_section: QueueSection::default(),
+            summary_section: SummarySection::default(),
+            theme: ThemeChoice::from_name(saved_settings.theme.as_deref()),
+            running_stitch: None,
+            restitch_pending: false,
             stitch_error: String::new(),
+            export_errors: vec![],
+            failed_export: None,
+            show_export_errors: false,
+            confirmed_outputs: HashSet::new(),
+            saved_settings,
+            settings_error,
+            watch_error: None,
         }
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
+    PresetSection(PresetSectionMessage),
     IOSection(IOSectionMessage),
     LimitSection(LimitSectionMessage),
     SettingSection(SettingSectionMessage),
+    PreviewSection(PreviewSectionMessage),
+    QueueSection(QueueSectionMessage),
+    SummarySection(SummarySectionMessage),
     Stitch,
+    Preview,
+    CancelStitch,
+    StitchProgress(StitchProgress),
+    AddToQueue,
+    ToggleExportErrors,
+    /// Exports the loaded strip again after some pages failed to export.
+    RetryExport,
+    /// Progress of the job the queue is running.
+    QueueProgress(StitchProgress),
+    InputChanged,
+    WatchError(String),
+    ResetSettings,
+    SetTheme(ThemeChoice),
+}
+
+impl Message {
+    /// Whether handling the message can change the saved settings. Progress
+    /// and other frequent messages skip comparing and saving them.
+    fn changes_settings(&self) -> bool {
+        match self {
+            Message::IOSection(message) => !matches!(
+                message,
+                IOSectionMessage::FileHovered
+                    | IOSectionMessage::FilesHoveredLeft
+                    | IOSectionMessage::ImageInspected(..)
+            ),
+            Message::PresetSection(message) => {
+                matches!(message, PresetSectionMessage::Select(_))
+            }
+            Message::LimitSection(_)
+            | Message::SettingSection(_)
+            | Message::ResetSettings
+            | Message::SetTheme(_) => true,
+            _ => false,
+        }
+    }
 }
 
 impl Quickstitch {
@@ -58,9 +216,16 @@ impl Quickstitch {
         // Text 16
         scrollable(
             column![
+                // Presets
+                row![list_icon().size(32), text("Presets").size(32)].spacing(10),
+                self.preset_section.view().map(Message::PresetSection),
+                horizontal_rule(3),
                 // Input/Output directories
                 row![folder_icon().size(32), text("I/O").size(32)].spacing(10),
                 self.io_section.view().map(Message::IOSection),
+                text(self.watch_error().unwrap_or_default())
+                    .size(16)
+                    .style(style::danger_text),
                 horizontal_rule(3),
                 // Image limits
                 row![image_icon().size(32), text("Size Limits").size(32)].spacing(10),
@@ -73,27 +238,220 @@ impl Quickstitch {
                 ]
                 .spacing(10),
                 self.setting_section.view().map(Message::SettingSection),
+                row![
+                    text(self.settings_error.as_deref().unwrap_or_default())
+                        .size(16)
+                        .style(style::danger_text)
+                        .width(FillPortion(1)),
+                    pick_list(ThemeChoice::all(), Some(&self.theme), Message::SetTheme)
+                        .text_size(20),
+                    button(text("Reset to Defaults").size(20))
+                        .on_press(Message::ResetSettings)
+                        .style(button::secondary),
+                ]
+                .spacing(20),
                 horizontal_rule(3),
                 column![
-                    button(row![
-                        Space::with_width(FillPortion(1)),
-                        text("Stitch").size(32),
-                        Space::with_width(FillPortion(1))
-                    ])
-                    .on_press(Message::Stitch)
-                    .width(FillPortion(1)),
-                    text(&self.stitch_error).size(16).style(text::danger),
+                    match &self.running_stitch {
+                        Some(running) => self.progress_view(running),
+                        None => row![
+                            button(row![
+                                Space::with_width(FillPortion(1)),
+                                text("Preview").size(32),
+                                Space::with_width(FillPortion(1))
+                            ])
+                            .on_press(Message::Preview)
+                            .style(button::secondary)
+                            .width(FillPortion(1)),
+                            button(row![
+                                Space::with_width(FillPortion(1)),
+                                text("Stitch").size(32),
+                                Space::with_width(FillPortion(1))
+                            ])
+                            .on_press(Message::Stitch)
+                            .width(FillPortion(1)),
+                            button(row![
+                                Space::with_width(FillPortion(1)),
+                                text("Add to Queue").size(32),
+                                Space::with_width(FillPortion(1))
+                            ])
+                            .on_press(Message::AddToQueue)
+                            .style(button::secondary)
+                            .width(FillPortion(1)),
+                        ]
+                        .spacing(20)
+                        .into(),
+                    },
+                    text(&self.stitch_error).size(16).style(style::danger_text),
+                    self.export_errors_view(),
                 ]
+                .spacing(10),
+                self.summary_view(),
+                self.queue_view(),
+                self.preview_view(),
             ]
             .spacing(20)
             .padding(20),
         )
         .into()
     }
-    pub fn update(&mut self, message: Message) {
+    fn export_errors_view(&self) -> Element<Message> {
+        if self.export_errors.is_empty() {
+            return column![].into();
+        }
+        let mut errors = column![
+            row![
+                button(
+                    text(if self.show_export_errors {
+                        "Hide Details"
+                    } else {
+                        "Show Details"
+                    })
+                    .size(20)
+                )
+                .on_press(Message::ToggleExportErrors)
+                .style(button::secondary)
+                .width(FillPortion(1)),
+                button(text("Retry Export").size(20))
+                    .on_press_maybe(
+                        (self.running_stitch.is_none() && self.failed_export.is_some())
+                            .then_some(Message::RetryExport)
+                    )
+                    .width(FillPortion(1)),
+            ]
+            .spacing(20)
+        ]
+        .spacing(10);
+        if self.show_export_errors {
+            for error in &self.export_errors {
+                let page = match error.page {
+                    Some(page) => format!("Page {page}"),
+                    None => "Archive".to_string(),
+                };
+                errors = errors.push(column![
+                    text(format!("{page} - {}", error.path.display())).size(16),
+                    text(&error.reason).size(16).style(style::danger_text),
+                ]);
+            }
+        }
+        errors.into()
+    }
+    fn summary_view(&self) -> Element<Message> {
+        if !self.summary_section.has_summary() {
+            return column![].into();
+        }
+        column![
+            horizontal_rule(3),
+            row![image_icon().size(32), text("Result").size(32)].spacing(10),
+            self.summary_section.view().map(Message::SummarySection),
+        ]
+        .spacing(20)
+        .into()
+    }
+    fn queue_view(&self) -> Element<Message> {
+        if self.queue_section.is_empty() {
+            return column![].into();
+        }
+        column![
+            horizontal_rule(3),
+            row![list_icon().size(32), text("Queue").size(32)].spacing(10),
+            self.queue_section.view().map(Message::QueueSection),
+        ]
+        .spacing(20)
+        .into()
+    }
+    fn preview_view(&self) -> Element<Message> {
+        if !self.preview_section.has_preview() {
+            return column![].into();
+        }
+        column![
+            horizontal_rule(3),
+            row![image_icon().size(32), text("Preview").size(32)].spacing(10),
+            self.preview_section
+                .view(self.running_stitch.is_none())
+                .map(Message::PreviewSection),
+        ]
+        .spacing(20)
+        .into()
+    }
+    fn progress_view(&self, running: &RunningStitch) -> Element<Message> {
+        let (status, done) = match &running.progress {
+            Some(Progress::Loading { loaded, total }) => (
+                format!("Loading images ({loaded}/{total})"),
+                *loaded as f32 / (*total).max(1) as f32,
+            ),
+            Some(Progress::FindingSplitpoints) => ("Finding splitpoints".to_string(), 1.0),
+            Some(Progress::Exporting { exported, total }) => (
+                format!("Exporting pages ({exported}/{total})"),
+                *exported as f32 / (*total).max(1) as f32,
+            ),
+            None => ("Starting".to_string(), 0.0),
+        };
+        column![
+            row![
+                text(status).size(20).width(FillPortion(1)),
+                button(text("Cancel").size(20))
+                    .on_press(Message::CancelStitch)
+                    .style(style::danger_button)
+            ]
+            .spacing(20),
+            progress_bar(0.0..=1.0, done),
+        ]
+        .spacing(10)
+        .into()
+    }
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        let changes_settings = message.changes_settings();
+        let task = self.handle(message);
+        if changes_settings {
+            self.save_settings();
+        }
+        task
+    }
+    /// The settings as currently shown in every section.
+    fn settings(&self) -> Settings {
+        let mut settings = self.saved_settings.clone();
+        self.io_section.save(&mut settings);
+        self.limit_section.save(&mut settings);
+        self.setting_section.save(&mut settings);
+        settings.theme = self.theme.name();
+        settings
+    }
+    fn apply_settings(&mut self, settings: &Settings) {
+        self.theme = ThemeChoice::from_name(settings.theme.as_deref());
+        self.io_section.apply(settings);
+        self.limit_section.apply(settings);
+        self.setting_section.apply(settings);
+    }
+    /// Saves the settings whenever they differ from what was last saved.
+    fn save_settings(&mut self) {
+        let settings = self.settings();
+        if settings == self.saved_settings {
+            return;
+        }
+        if let Some(path) = settings::settings_path() {
+            self.settings_error = settings::save(&path, &settings)
+                .err()
+                .map(|e| e.to_string());
+        }
+        self.saved_settings = settings;
+    }
+    fn handle(&mut self, message: Message) -> Task<Message> {
         match message {
+            Message::PresetSection(PresetSectionMessage::Select(name)) => {
+                if let Some(preset) = self.preset_section.select(name) {
+                    let mut settings = self.settings();
+                    preset.apply(&mut settings);
+                    self.apply_settings(&settings);
+                }
+            }
+            Message::PresetSection(PresetSectionMessage::Save) => {
+                let preset = Preset::from_settings(&self.settings());
+                self.preset_section.save_preset(preset);
+            }
+            Message::PresetSection(preset_message) => self.preset_section.update(preset_message),
             Message::IOSection(io_message) => {
-                self.io_section.update(io_message);
+                return self.io_section.update(io_message).map(Message::IOSection);
             }
             Message::LimitSection(limit_section_message) => {
                 self.limit_section.update(limit_section_message);
@@ -101,34 +459,349 @@ impl Quickstitch {
             Message::SettingSection(setting_section_message) => {
                 self.setting_section.update(setting_section_message);
             }
-            Message::Stitch => {
-                match stitcher(
-                    self.io_section.input_type(),
-                    self.io_section.input_directory(),
-                    self.io_section.sort_method(),
-                    self.io_section.input_files(),
-                    self.io_section.ignore_unlodable(),
-                    self.io_section.output_directory(),
-                    self.io_section.output_format().borrow().clone(),
-                    self.io_section.compression_quality(),
-                    self.limit_section.width_type(),
-                    self.limit_section.fixed_width(),
-                    self.limit_section.max_height(),
-                    self.limit_section.min_height(),
-                    self.setting_section.scan_interval(),
-                    self.setting_section.sensitivity(),
-                    self.setting_section.debug(),
-                ) {
+            Message::PreviewSection(PreviewSectionMessage::Redetect) => {
+                if let Some((strip, _)) = self.preview_section.plan() {
+                    return self.start_run(false, None, |job, _| detect(job, strip));
+                }
+            }
+            Message::ToggleExportErrors => self.show_export_errors = !self.show_export_errors,
+            Message::PreviewSection(PreviewSectionMessage::Export) => {
+                if let Some((strip, splitpoints)) = self.preview_section.plan() {
+                    let exporting = (strip.clone(), splitpoints.clone());
+                    return self.start_run(true, Some(exporting), |job, cancel| {
+                        export(job, strip, splitpoints, cancel)
+                    });
+                }
+            }
+            Message::RetryExport => {
+                // Kept until the retry starts, in case overwriting is declined.
+                if let Some(failed) = self.failed_export.clone() {
+                    let exporting = (failed.strip.clone(), failed.splitpoints.clone());
+                    return self.start_job(true, failed.job, Some(exporting), |job, cancel| {
+                        export(job, failed.strip, failed.splitpoints, cancel)
+                    });
+                }
+            }
+            Message::PreviewSection(preview_section_message) => {
+                self.preview_section
+                    .update(preview_section_message, self.running_stitch.is_none());
+            }
+            Message::QueueSection(queue_message) => {
+                self.queue_section.update(queue_message);
+                return self.run_queue();
+            }
+            Message::AddToQueue => match self.stitch_job() {
+                Ok(job) if !self.confirm_overwrite(&job) => {}
+                Ok(job) => {
+                    self.queue_section.push(job);
+                    self.stitch_error = String::new();
+                    return self.run_queue();
+                }
+                Err(e) => self.stitch_error = e.to_string(),
+            },
+            Message::QueueProgress(StitchProgress::Running(progress)) => {
+                self.queue_section.set_progress(progress);
+            }
+            Message::QueueProgress(StitchProgress::Previewed(_) | StitchProgress::Exported(_)) => {}
+            Message::QueueProgress(StitchProgress::Finished(result)) => {
+                self.queue_section.finish(result);
+                return self.run_queue();
+            }
+            Message::Stitch => return self.start_run(true, None, stitch),
+            Message::Preview => return self.start_run(false, None, preview),
+            Message::CancelStitch => {
+                if let Some(running) = &self.running_stitch {
+                    running.cancel.cancel();
+                }
+            }
+            Message::StitchProgress(StitchProgress::Finished(result)) => {
+                let running = self.running_stitch.take();
+                match result {
                     Ok(splitpoints) => {
-                        self.splitpoints = Some(splitpoints);
+                        self.preview_section.set_splitpoints(splitpoints);
                         self.stitch_error = String::new();
                     }
+                    Err(StitchError::Export(errors)) => {
+                        self.stitch_error = format!(
+                            "Exporting failed with {} errors. The loaded images and cuts are \
+                             kept, so the export can be retried.",
+                            errors.len()
+                        );
+                        self.export_errors = errors;
+                        self.failed_export = running.and_then(|running| {
+                            let (strip, splitpoints) = running.exporting?;
+                            Some(FailedExport {
+                                job: running.job,
+                                strip,
+                                splitpoints,
+                            })
+                        });
+                    }
                     Err(e) => self.stitch_error = e.to_string(),
                 }
+                if std::mem::take(&mut self.restitch_pending) {
+                    return self.handle(Message::Stitch);
+                }
+            }
+            Message::StitchProgress(StitchProgress::Previewed(preview)) => {
+                self.preview_section.set_preview(preview);
+                // A full stitch goes on to export the cuts it just found.
+                if let Some(running) = &mut self.running_stitch {
+                    running.exporting = self.preview_section.plan();
+                }
+            }
+            Message::StitchProgress(StitchProgress::Exported(summary)) => {
+                self.summary_section.set_summary(Some(summary));
+            }
+            Message::SummarySection(summary_message) => {
+                return self
+                    .summary_section
+                    .update(summary_message)
+                    .map(Message::SummarySection);
+            }
+            Message::StitchProgress(StitchProgress::Running(progress)) => {
+                if let Some(running) = &mut self.running_stitch {
+                    running.progress = Some(progress);
+                }
+            }
+            Message::ResetSettings => {
+                // Recent directories are history rather than settings.
+                let defaults = Settings {
+                    recent_inputs: self.saved_settings.recent_inputs.clone(),
+                    recent_outputs: self.saved_settings.recent_outputs.clone(),
+                    ..Settings::default()
+                };
+                self.apply_settings(&defaults);
+            }
+            Message::SetTheme(theme) => self.theme = theme,
+            Message::WatchError(e) => {
+                self.watch_error = self.io_section.watched_directory().map(|dir| (dir, e));
+            }
+            Message::InputChanged => {
+                self.watch_error = None;
+                // Changes made mid-stitch are picked up once it finishes
+                // rather than cancelling it.
+                if self.running_stitch.is_some() {
+                    self.restitch_pending = true;
+                } else {
+                    return self.handle(Message::Stitch);
+                }
+            }
+        }
+        Task::none()
+    }
+    /// Starts a run with a job built from the current settings, or shows why
+    /// one can't be built. Runs that `write` pages ask before overwriting, and
+    /// runs that export an already loaded strip pass it as `exporting`.
+    fn start_run<S>(
+        &mut self,
+        write: bool,
+        exporting: Option<(Arc<Strip>, Splitpoints)>,
+        run: impl FnOnce(StitchJob, CancelToken) -> S,
+    ) -> Task<Message>
+    where
+        S: Stream<Item = StitchProgress> + Send + 'static,
+    {
+        match self.stitch_job() {
+            Ok(job) => self.start_job(write, job, exporting, run),
+            Err(e) => {
+                self.stitch_error = e.to_string();
+                Task::none()
             }
         }
     }
-    pub fn get_theme(&self) -> Theme {
-        self.theme.clone()
+    /// Like [`Self::start_run`], with the job already built.
+    fn start_job<S>(
+        &mut self,
+        write: bool,
+        job: StitchJob,
+        exporting: Option<(Arc<Strip>, Splitpoints)>,
+        run: impl FnOnce(StitchJob, CancelToken) -> S,
+    ) -> Task<Message>
+    where
+        S: Stream<Item = StitchProgress> + Send + 'static,
+    {
+        if write && !self.confirm_overwrite(&job) {
+            return Task::none();
+        }
+        let cancel = CancelToken::default();
+        self.running_stitch = Some(RunningStitch {
+            job: job.clone(),
+            cancel: cancel.clone(),
+            progress: None,
+            exporting,
+        });
+        self.stitch_error = String::new();
+        self.export_errors.clear();
+        self.failed_export = None;
+        self.summary_section.set_summary(None);
+        Task::run(run(job, cancel), Message::StitchProgress)
+    }
+    /// Asks before a job overwrites files already in its output directory.
+    /// The other policies never replace anything the user didn't ask for, so
+    /// they go ahead without asking.
+    fn confirm_overwrite(&mut self, job: &StitchJob) -> bool {
+        if job.on_existing != OnExisting::Overwrite
+            || !has_existing_output(&job.output)
+            || self.confirmed_outputs.contains(&job.output)
+        {
+            return true;
+        }
+        let overwrite = "Overwrite".to_string();
+        let answer = MessageDialog::new()
+            .set_level(MessageLevel::Warning)
+            .set_title("Overwrite existing files?")
+            .set_description(format!(
+                "{} already has files in it. Pages with the same names will be replaced.",
+                job.output.display()
+            ))
+            .set_buttons(MessageButtons::OkCancelCustom(
+                overwrite.clone(),
+                "Cancel".to_string(),
+            ))
+            .show();
+        // Some platforms can't label the buttons and answer with plain `Ok`.
+        let confirmed =
+            answer == MessageDialogResult::Custom(overwrite) || answer == MessageDialogResult::Ok;
+        if confirmed {
+            self.confirmed_outputs.insert(job.output.clone());
+        }
+        confirmed
+    }
+    /// Starts the next queued job, unless one is already running.
+    fn run_queue(&mut self) -> Task<Message> {
+        match self.queue_section.start_next() {
+            Some((job, cancel)) => Task::run(stitch_queued(job, cancel), Message::QueueProgress),
+            None => Task::none(),
+        }
+    }
+    fn stitch_job(&self) -> Result<StitchJob, StitcherError> {
+        let mut job = self.io_section.stitch_job()?;
+        self.limit_section.configure(&mut job)?;
+        self.setting_section.configure(&mut job)?;
+        job.validate()?;
+        Ok(job)
+    }
+    pub fn subscription(&self) -> Subscription<Message> {
+        let file_drops = event::listen_with(|event, _, _| match event {
+            Event::Window(window::Event::FileHovered(_)) => Some(IOSectionMessage::FileHovered),
+            Event::Window(window::Event::FilesHoveredLeft) => {
+                Some(IOSectionMessage::FilesHoveredLeft)
+            }
+            Event::Window(window::Event::FileDropped(path)) => {
+                Some(IOSectionMessage::FileDropped(path))
+            }
+            _ => None,
+        })
+        .map(Message::IOSection);
+        let watch = match self.io_section.watched_directory() {
+            // Exported pages would otherwise be picked up as new inputs.
+            Some(input) if Some(&input) != self.io_section.output_directory().as_ref() => {
+                Subscription::run_with(input, watch).map(|result| match result {
+                    Ok(()) => Message::InputChanged,
+                    Err(e) => Message::WatchError(e),
+                })
+            }
+            _ => Subscription::none(),
+        };
+        Subscription::batch([file_drops, watch])
+    }
+    /// The error from watching the input directory, if it's still watched.
+    fn watch_error(&self) -> Option<&str> {
+        let (dir, e) = self.watch_error.as_ref()?;
+        (self.io_section.watched_directory().as_ref() == Some(dir)).then_some(e.as_str())
+    }
+    /// `None` lets iced pick the light or dark theme to match the system.
+    pub fn get_theme(&self) -> Option<Theme> {
+        match &self.theme {
+            ThemeChoice::System => None,
+            ThemeChoice::Theme(theme) => Some(theme.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use ::image::RgbImage;
+    use iced::futures::stream;
+    use quickstitch_app::{DEFAULT_NAME_TEMPLATE, Input, OutputFormat, Sort};
+
+    use super::*;
+    use crate::gui::preview_section::Preview;
+
+    fn job() -> StitchJob {
+        StitchJob {
+            input: Input::Images(vec![PathBuf::from("1.png")]),
+            sort: Sort::Natural,
+            ignore_unloadable: true,
+            // A name no platform accepts, so the retried export fails before
+            // writing anything.
+            output: PathBuf::from("stitched\0"),
+            on_existing: OnExisting::Overwrite,
+            format: OutputFormat::Png,
+            comic_info: None,
+            name_template: DEFAULT_NAME_TEMPLATE.to_string(),
+            width: None,
+            max_height: 100,
+            min_height: 0,
+            scan_interval: 5,
+            sensitivity: 220,
+            debug: false,
+        }
+    }
+
+    fn cuts(quickstitch: &Quickstitch) -> Vec<usize> {
+        quickstitch.preview_section.plan().unwrap().1.cuts
+    }
+
+    #[test]
+    fn retry_exports_the_cuts_that_failed_even_after_edits() {
+        let mut quickstitch = Quickstitch::default();
+        let _ = quickstitch.start_job(true, job(), None, |_, _| stream::empty());
+        let strip = Arc::new(Strip {
+            image: RgbImage::new(10, 300),
+            inputs: vec![PathBuf::from("1.png")],
+            offsets: vec![0],
+            skipped: vec![],
+        });
+        let found = Splitpoints {
+            cuts: vec![100, 200],
+            skipped: vec![],
+        };
+        let _ = quickstitch.update(Message::StitchProgress(StitchProgress::Previewed(
+            Preview::new(strip, found),
+        )));
+
+        // Every kind of edit made while the pages are being exported.
+        for message in [
+            PreviewSectionMessage::MoveCursor(50.0 * 24.0),
+            PreviewSectionMessage::AddCut,
+            PreviewSectionMessage::StartDrag(1),
+            PreviewSectionMessage::MoveCursor(250.0 * 24.0),
+            PreviewSectionMessage::StopDrag,
+            PreviewSectionMessage::DeleteCut(0),
+            PreviewSectionMessage::DeleteCut(7),
+        ] {
+            let _ = quickstitch.update(Message::PreviewSection(message));
+        }
+        assert_eq!(cuts(&quickstitch), [100, 200]);
+
+        let _ = quickstitch.update(Message::StitchProgress(StitchProgress::Finished(Err(
+            StitchError::Export(vec![ExportError {
+                page: Some(2),
+                path: PathBuf::from("page_2.png"),
+                reason: "disk full".to_string(),
+            }]),
+        ))));
+        // Once the run is over, the cuts can be edited again.
+        let _ = quickstitch.update(Message::PreviewSection(PreviewSectionMessage::DeleteCut(0)));
+        assert_eq!(cuts(&quickstitch), [200]);
+
+        let _ = quickstitch.update(Message::RetryExport);
+        let running = quickstitch.running_stitch.as_ref().unwrap();
+        let (_, exporting) = running.exporting.as_ref().unwrap();
+        assert_eq!(exporting.cuts, [100, 200]);
     }
 }
@@ -0,0 +1,216 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use iced::{
+    Element,
+    Length::FillPortion,
+    widget::{button, column, pick_list, row, text, text_input},
+};
+use rfd::{FileDialog, MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
+
+use crate::gui::style;
+use crate::presets::{self, Preset};
+
+pub struct PresetSection {
+    /// Where the presets are kept, if the platform has a config directory.
+    path: Option<PathBuf>,
+    presets: BTreeMap<String, Preset>,
+    selected: Option<String>,
+    name_field: String,
+    error: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum PresetSectionMessage {
+    /// Handled by the parent, which applies the preset to every section.
+    Select(String),
+    SetName(String),
+    /// Handled by the parent, which knows the current settings.
+    Save,
+    Rename,
+    Delete,
+    Import,
+    Export,
+}
+
+impl PresetSection {
+    pub fn new() -> Self {
+        let path = presets::presets_path();
+        let (presets, error) = match path.as_deref().map(presets::load) {
+            Some(Ok(presets)) => (presets, None),
+            Some(Err(e)) => (BTreeMap::new(), Some(e.to_string())),
+            None => (BTreeMap::new(), None),
+        };
+        Self {
+            path,
+            presets,
+            selected: None,
+            name_field: String::new(),
+            error,
+        }
+    }
+    /// Selects the preset, returning it so it can be applied.
+    pub fn select(&mut self, name: String) -> Option<Preset> {
+        let preset = self.presets.get(&name).cloned();
+        self.name_field = name.clone();
+        self.selected = Some(name);
+        preset
+    }
+    /// Saves the preset under the name in the name field, replacing any
+    /// preset already saved under it.
+    pub fn save_preset(&mut self, preset: Preset) {
+        let name = self.name_field.trim().to_string();
+        if name.is_empty() {
+            self.error = Some("Enter a name to save the preset under".to_string());
+            return;
+        }
+        self.presets.insert(name.clone(), preset);
+        self.selected = Some(name);
+        self.persist();
+    }
+    /// The first of `<name> (2)`, `<name> (3)`, ... that isn't taken yet.
+    fn unused_name(&self, name: &str) -> String {
+        (2..)
+            .map(|n| format!("{name} ({n})"))
+            .find(|candidate| !self.presets.contains_key(candidate))
+            .expect("there are fewer presets than names")
+    }
+    fn persist(&mut self) {
+        self.error = self
+            .path
+            .as_deref()
+            .and_then(|path| presets::save(path, &self.presets).err())
+            .map(|e| e.to_string());
+    }
+    pub fn view(&self) -> Element<PresetSectionMessage> {
+        let names: Vec<String> = self.presets.keys().cloned().collect();
+        let selected_action = |label, message: PresetSectionMessage| {
+            button(text(label).size(20))
+                .style(button::secondary)
+                .on_press_maybe(self.selected.is_some().then_some(message))
+        };
+        column![
+            row![
+                pick_list(names, self.selected.clone(), PresetSectionMessage::Select)
+                    .placeholder("Choose a preset")
+                    .text_size(20)
+                    .width(FillPortion(1)),
+                text_input("Preset name", &self.name_field)
+                    .on_input(PresetSectionMessage::SetName)
+                    .size(20)
+                    .width(FillPortion(1)),
+                button(text("Save").size(20)).on_press(PresetSectionMessage::Save),
+            ]
+            .spacing(20),
+            row![
+                text(self.error.as_deref().unwrap_or_default())
+                    .size(16)
+                    .style(style::danger_text)
+                    .width(FillPortion(1)),
+                selected_action("Rename", PresetSectionMessage::Rename),
+                selected_action("Delete", PresetSectionMessage::Delete),
+                button(text("Import").size(20))
+                    .style(button::secondary)
+                    .on_press(PresetSectionMessage::Import),
+                selected_action("Export", PresetSectionMessage::Export),
+            ]
+            .spacing(10),
+        ]
+        .spacing(10)
+        .into()
+    }
+    pub fn update(&mut self, message: PresetSectionMessage) {
+        match message {
+            PresetSectionMessage::SetName(name) => self.name_field = name,
+            PresetSectionMessage::Rename => {
+                let name = self.name_field.trim().to_string();
+                let Some(old_name) = self.selected.take() else {
+                    return;
+                };
+                if name.is_empty() || self.presets.contains_key(&name) {
+                    self.error = Some(format!("Can't rename \"{old_name}\" to \"{name}\""));
+                    self.selected = Some(old_name);
+                    return;
+                }
+                if let Some(preset) = self.presets.remove(&old_name) {
+                    self.presets.insert(name.clone(), preset);
+                }
+                self.selected = Some(name);
+                self.persist();
+            }
+            PresetSectionMessage::Delete => {
+                if let Some(name) = self.selected.take() {
+                    self.presets.remove(&name);
+                    self.persist();
+                }
+            }
+            PresetSectionMessage::Import => {
+                let Some(path) = FileDialog::new()
+                    .add_filter("Preset (toml)", &["toml"])
+                    .pick_file()
+                else {
+                    return;
+                };
+                match presets::import(&path) {
+                    Ok(imported) if imported.is_empty() => {
+                        self.error = Some(format!("{} has no presets", path.display()));
+                    }
+                    Ok(imported) => {
+                        let mut first = None;
+                        for (name, preset) in imported {
+                            let name =
+                                match self.presets.contains_key(&name) && !confirm_replace(&name) {
+                                    true => self.unused_name(&name),
+                                    false => name,
+                                };
+                            first.get_or_insert_with(|| name.clone());
+                            self.presets.insert(name, preset);
+                        }
+                        self.name_field = first.clone().unwrap_or_default();
+                        self.selected = first;
+                        self.persist();
+                    }
+                    Err(e) => self.error = Some(e.to_string()),
+                }
+            }
+            PresetSectionMessage::Export => {
+                let Some((name, preset)) = self
+                    .selected
+                    .as_ref()
+                    .and_then(|name| self.presets.get_key_value(name))
+                else {
+                    return;
+                };
+                if let Some(path) = FileDialog::new()
+                    .add_filter("Preset (toml)", &["toml"])
+                    .set_file_name(format!("{name}.toml"))
+                    .save_file()
+                {
+                    self.error = presets::export(&path, name, preset)
+                        .err()
+                        .map(|e| e.to_string());
+                }
+            }
+            PresetSectionMessage::Select(_) | PresetSectionMessage::Save => {}
+        }
+    }
+}
+
+/// Asks whether an imported preset should replace the one with the same name,
+/// rather than being kept alongside it under a new name.
+fn confirm_replace(name: &str) -> bool {
+    let replace = "Replace".to_string();
+    let answer = MessageDialog::new()
+        .set_level(MessageLevel::Warning)
+        .set_title("Replace preset?")
+        .set_description(format!(
+            "A preset named \"{name}\" already exists. Replace it, or keep both and \
+             import this one under a new name?"
+        ))
+        .set_buttons(MessageButtons::OkCancelCustom(
+            replace.clone(),
+            "Keep Both".to_string(),
+        ))
+        .show();
+    // Some platforms can't label the buttons and answer with plain `Ok`.
+    answer == MessageDialogResult::Custom(replace) || answer == MessageDialogResult::Ok
+}
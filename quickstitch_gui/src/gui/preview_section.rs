@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use ::image::{DynamicImage, imageops};
+use iced::{
+    Alignment, Element,
+    Length::{Fill, FillPortion},
+    mouse::Interaction,
+    widget::{Space, button, column, container, image, mouse_area, row, scrollable, stack, text},
+};
+use quickstitch_app::{Splitpoints, Strip};
+
+/// The width, in pixels, the stitched strip is scaled down to.
+const PREVIEW_WIDTH: u32 = 240;
+/// The strip is split into tiles no taller than this, since very tall images
+/// can't be uploaded to the GPU in one piece.
+const MAX_TILE_HEIGHT: u32 = 4096;
+/// The height of the area around a cut line that can be grabbed.
+const GRAB_HEIGHT: f32 = 9.0;
+
+/// A loaded strip, a scaled-down copy of it and the rows it will be cut at.
+#[derive(Debug, Clone)]
+pub struct Preview {
+    strip: Arc<Strip>,
+    tiles: Vec<image::Handle>,
+    /// Preview pixels per row of the full-size strip.
+    scale: f32,
+    splitpoints: Splitpoints,
+}
+
+impl Preview {
+    pub fn new(strip: Arc<Strip>, splitpoints: Splitpoints) -> Self {
+        let (width, height) = strip.image.dimensions();
+        let scaled_height =
+            (height as u64 * PREVIEW_WIDTH as u64 / width.max(1) as u64).max(1) as u32;
+        let scaled = imageops::thumbnail(&strip.image, PREVIEW_WIDTH, scaled_height);
+        let tiles = (0..scaled_height)
+            .step_by(MAX_TILE_HEIGHT as usize)
+            .map(|top| {
+                let tile_height = MAX_TILE_HEIGHT.min(scaled_height - top);
+                let tile = imageops::crop_imm(&scaled, 0, top, PREVIEW_WIDTH, tile_height);
+                let tile = DynamicImage::ImageRgb8(tile.to_image()).into_rgba8();
+                image::Handle::from_rgba(tile.width(), tile.height(), tile.into_raw())
+            })
+            .collect();
+        Self {
+            strip,
+            tiles,
+            scale: scaled_height as f32 / height.max(1) as f32,
+            splitpoints,
+        }
+    }
+
+    fn height(&self) -> usize {
+        self.strip.image.height() as usize
+    }
+
+    /// The row of the full-size strip under the given preview position.
+    fn row_at(&self, y: f32) -> usize {
+        ((y / self.scale).round().max(0.0) as usize).min(self.height())
+    }
+
+    /// The row of the full-size strip at which each page starts and ends.
+    fn bounds(&self) -> Vec<(usize, usize)> {
+        let rows: Vec<usize> = [0]
+            .into_iter()
+            .chain(self.splitpoints.cuts.iter().copied())
+            .chain([self.height()])
+            .collect();
+        rows.windows(2).map(|page| (page[0], page[1])).collect()
+    }
+}
+
+#[derive(Default)]
+pub struct PreviewSection {
+    preview: Option<Preview>,
+    /// Where the cursor last was over the strip, in preview pixels.
+    cursor: f32,
+    /// The cut currently being dragged.
+    dragging: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PreviewSectionMessage {
+    MoveCursor(f32),
+    AddCut,
+    StartDrag(usize),
+    StopDrag,
+    DeleteCut(usize),
+    /// Handled by the parent, which knows the current settings.
+    Redetect,
+    /// Handled by the parent, which knows the current settings.
+    Export,
+}
+
+impl PreviewSection {
+    pub fn set_preview(&mut self, preview: Preview) {
+        self.preview = Some(preview);
+        self.dragging = None;
+    }
+    pub fn set_splitpoints(&mut self, splitpoints: Splitpoints) {
+        if let Some(preview) = &mut self.preview {
+            preview.splitpoints = splitpoints;
+        }
+        self.dragging = None;
+    }
+    pub fn has_preview(&self) -> bool {
+        self.preview.is_some()
+    }
+    /// The previewed strip along with its cuts as edited by the user.
+    pub fn plan(&self) -> Option<(Arc<Strip>, Splitpoints)> {
+        self.preview
+            .as_ref()
+            .map(|preview| (preview.strip.clone(), preview.splitpoints.clone()))
+    }
+    pub fn view(&self, idle: bool) -> Element<PreviewSectionMessage> {
+        let Some(preview) = &self.preview else {
+            return column![].into();
+        };
+        let cuts = &preview.splitpoints.cuts;
+
+        // Cut lines are laid over the strip, each centered in a grabbable area.
+        let mut lines = column![];
+        let mut top = 0.0;
+        for (i, &cut) in cuts.iter().enumerate() {
+            let grab_top = (cut as f32 * preview.scale - GRAB_HEIGHT / 2.0).max(top);
+            let line = mouse_area(
+                container(
+                    container(Space::with_height(3))
+                        .width(Fill)
+                        .style(container::danger),
+                )
+                .height(GRAB_HEIGHT)
+                .align_y(Alignment::Center),
+            );
+            let line = match idle {
+                true => line
+                    .on_press(PreviewSectionMessage::StartDrag(i))
+                    .on_right_press(PreviewSectionMessage::DeleteCut(i))
+                    .interaction(Interaction::ResizingVertically),
+                false => line,
+            };
+            lines = lines.push(Space::with_height(grab_top - top)).push(line);
+            top = grab_top + GRAB_HEIGHT;
+        }
+        let strip = mouse_area(stack![
+            column(preview.tiles.iter().map(|tile| image(tile.clone()).into())),
+            lines.width(Fill),
+        ])
+        .on_move(|position| PreviewSectionMessage::MoveCursor(position.y))
+        .on_release(PreviewSectionMessage::StopDrag)
+        .on_exit(PreviewSectionMessage::StopDrag);
+        // Cuts can't be edited while a run may be exporting them.
+        let strip = match idle {
+            true => strip
+                .on_press(PreviewSectionMessage::AddCut)
+                .interaction(Interaction::Crosshair),
+            false => strip,
+        };
+
+        let labels = column(
+            preview
+                .bounds()
+                .into_iter()
+                .enumerate()
+                .map(|(i, (start, end))| {
+                    container(column![
+                        text(format!("Page {}", i + 1)).size(20),
+                        text(format!("{} pixels tall", end - start))
+                            .size(16)
+                            .style(text::secondary),
+                    ])
+                    .height((end - start) as f32 * preview.scale)
+                    .clip(true)
+                    .into()
+                }),
+        );
+
+        column![
+            text("Click to add a cut, drag a cut to move it and right-click a cut to remove it.")
+                .size(16)
+                .style(text::secondary),
+            scrollable(row![strip, labels].spacing(20))
+                .height(500)
+                .width(Fill),
+            row![
+                button(text("Detect Cuts Again").size(20))
+                    .on_press_maybe(idle.then_some(PreviewSectionMessage::Redetect))
+                    .style(button::secondary)
+                    .width(FillPortion(1)),
+                button(text(format!("Export {} Pages", cuts.len() + 1)).size(20))
+                    .on_press_maybe(idle.then_some(PreviewSectionMessage::Export))
+                    .width(FillPortion(1)),
+            ]
+            .spacing(20),
+        ]
+        .spacing(10)
+        .into()
+    }
+    /// Cuts are only edited while `idle`, since a running export may still be
+    /// writing them.
+    pub fn update(&mut self, message: PreviewSectionMessage, idle: bool) {
+        let Some(preview) = &mut self.preview else {
+            return;
+        };
+        if !idle {
+            self.dragging = None;
+        }
+        match message {
+            PreviewSectionMessage::MoveCursor(y) => {
+                self.cursor = y;
+                if let Some(i) = self.dragging {
+                    // Cuts can't be dragged past their neighbours, which keeps
+                    // them sorted and every page at least one row tall.
+                    let row = preview.row_at(y);
+                    let cuts = &preview.splitpoints.cuts;
+                    let above = if i == 0 { 0 } else { cuts[i - 1] };
+                    let below = cuts.get(i + 1).copied().unwrap_or(preview.height());
+                    if below - above > 1 {
+                        preview.splitpoints.cuts[i] = row.clamp(above + 1, below - 1);
+                    }
+                }
+            }
+            PreviewSectionMessage::AddCut if idle => {
+                let row = preview.row_at(self.cursor);
+                let height = preview.height();
+                let cuts = &mut preview.splitpoints.cuts;
+                if let Err(i) = cuts.binary_search(&row)
+                    && row > 0
+                    && row < height
+                {
+                    cuts.insert(i, row);
+                }
+            }
+            PreviewSectionMessage::StartDrag(i) if idle && i < preview.splitpoints.cuts.len() => {
+                self.dragging = Some(i);
+            }
+            PreviewSectionMessage::StopDrag => self.dragging = None,
+            PreviewSectionMessage::DeleteCut(i) if idle && i < preview.splitpoints.cuts.len() => {
+                preview.splitpoints.cuts.remove(i);
+                self.dragging = None;
+            }
+            PreviewSectionMessage::AddCut
+            | PreviewSectionMessage::StartDrag(_)
+            | PreviewSectionMessage::DeleteCut(_)
+            | PreviewSectionMessage::Redetect
+            | PreviewSectionMessage::Export => {}
+        }
+    }
+}
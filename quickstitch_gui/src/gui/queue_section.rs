@@ -0,0 +1,210 @@
+use std::path::Path;
+
+use iced::{
+    Element,
+    Length::Fill,
+    widget::{Text, button, column, row, text},
+};
+use quickstitch_app::{CancelToken, Input, Progress, Splitpoints, StitchError, StitchJob};
+
+use crate::gui::{
+    icons::{arrow_down_icon, arrow_up_icon, delete_icon},
+    style,
+};
+
+/// A snapshot of the settings and inputs at the time it was queued.
+struct QueuedJob {
+    job: StitchJob,
+    status: JobStatus,
+}
+
+enum JobStatus {
+    Pending,
+    Running(Option<Progress>),
+    Done { pages: usize },
+    Failed(String),
+}
+
+#[derive(Default)]
+pub struct QueueSection {
+    jobs: Vec<QueuedJob>,
+    /// Stops the running job, if there is one.
+    cancel: Option<CancelToken>,
+}
+
+#[derive(Clone, Debug)]
+pub enum QueueSectionMessage {
+    MoveUp(usize),
+    MoveDown(usize),
+    Remove(usize),
+    /// Puts a failed job back in the queue.
+    Retry(usize),
+    Cancel,
+    ClearFinished,
+}
+
+impl QueuedJob {
+    fn name(&self) -> String {
+        let file_name = |path: &Path| {
+            path.file_name()
+                .unwrap_or(path.as_os_str())
+                .display()
+                .to_string()
+        };
+        match &self.job.input {
+            Input::Directory(path) | Input::Archive(path) | Input::ArchiveEntries(path, _) => {
+                file_name(path)
+            }
+            Input::Images(images) => format!("{} images", images.len()),
+        }
+    }
+    fn status(&self) -> Text<'static> {
+        match &self.status {
+            JobStatus::Pending => text("Pending").style(text::secondary),
+            JobStatus::Running(progress) => text(match progress {
+                Some(Progress::Loading { loaded, total }) => {
+                    format!("Loading images ({loaded}/{total})")
+                }
+                Some(Progress::FindingSplitpoints) => "Finding splitpoints".to_string(),
+                Some(Progress::Exporting { exported, total }) => {
+                    format!("Exporting pages ({exported}/{total})")
+                }
+                None => "Starting".to_string(),
+            })
+            .style(text::primary),
+            JobStatus::Done { pages } => text(format!("Done, {pages} pages")).style(text::success),
+            JobStatus::Failed(reason) => {
+                text(format!("Failed: {reason}")).style(style::danger_text)
+            }
+        }
+    }
+}
+
+impl QueueSection {
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+    pub fn push(&mut self, job: StitchJob) {
+        self.jobs.push(QueuedJob {
+            job,
+            status: JobStatus::Pending,
+        });
+    }
+    /// Marks the first pending job as running and returns it, unless a job is
+    /// already running.
+    pub fn start_next(&mut self) -> Option<(StitchJob, CancelToken)> {
+        if self.cancel.is_some() {
+            return None;
+        }
+        let queued = self
+            .jobs
+            .iter_mut()
+            .find(|queued| matches!(queued.status, JobStatus::Pending))?;
+        queued.status = JobStatus::Running(None);
+        let cancel = CancelToken::default();
+        self.cancel = Some(cancel.clone());
+        Some((queued.job.clone(), cancel))
+    }
+    pub fn set_progress(&mut self, progress: Progress) {
+        if let Some(queued) = self.running() {
+            queued.status = JobStatus::Running(Some(progress));
+        }
+    }
+    pub fn finish(&mut self, result: Result<Splitpoints, StitchError>) {
+        if let Some(queued) = self.running() {
+            queued.status = match result {
+                Ok(splitpoints) => JobStatus::Done {
+                    pages: splitpoints.cuts.len() + 1,
+                },
+                Err(e) => JobStatus::Failed(e.to_string()),
+            };
+        }
+        self.cancel = None;
+    }
+    fn running(&mut self) -> Option<&mut QueuedJob> {
+        self.jobs
+            .iter_mut()
+            .find(|queued| matches!(queued.status, JobStatus::Running(_)))
+    }
+    pub fn view(&self) -> Element<QueueSectionMessage> {
+        let icon_button = |icon: Text<'static>, message: Option<QueueSectionMessage>| {
+            button(icon.size(20))
+                .style(button::text)
+                .on_press_maybe(message)
+        };
+        let jobs = self.jobs.iter().enumerate().map(|(i, queued)| {
+            let pending = matches!(queued.status, JobStatus::Pending);
+            // Pending jobs can only be moved among each other.
+            let can_move = |j: Option<usize>| {
+                pending
+                    && j.and_then(|j| self.jobs.get(j))
+                        .is_some_and(|other| matches!(other.status, JobStatus::Pending))
+            };
+            let action: Element<_> = match queued.status {
+                JobStatus::Running(_) => button(text("Cancel").size(16))
+                    .style(style::danger_button)
+                    .on_press(QueueSectionMessage::Cancel)
+                    .into(),
+                JobStatus::Failed(_) => button(text("Retry").size(16))
+                    .style(button::secondary)
+                    .on_press(QueueSectionMessage::Retry(i))
+                    .into(),
+                JobStatus::Pending | JobStatus::Done { .. } => button(delete_icon().size(20))
+                    .style(style::danger_button)
+                    .on_press(QueueSectionMessage::Remove(i))
+                    .into(),
+            };
+            row![
+                column![
+                    text(queued.name()).size(20),
+                    text(queued.job.output.display().to_string())
+                        .size(16)
+                        .style(text::secondary),
+                    queued.status().size(16),
+                ]
+                .width(Fill),
+                icon_button(
+                    arrow_up_icon(),
+                    can_move(i.checked_sub(1)).then_some(QueueSectionMessage::MoveUp(i))
+                ),
+                icon_button(
+                    arrow_down_icon(),
+                    can_move(Some(i + 1)).then_some(QueueSectionMessage::MoveDown(i))
+                ),
+                action,
+            ]
+            .spacing(10)
+            .into()
+        });
+        column![
+            column(jobs).spacing(10),
+            button(text("Clear Finished").size(20))
+                .style(button::secondary)
+                .on_press(QueueSectionMessage::ClearFinished),
+        ]
+        .spacing(20)
+        .into()
+    }
+    pub fn update(&mut self, message: QueueSectionMessage) {
+        match message {
+            QueueSectionMessage::MoveUp(i) => self.jobs.swap(i - 1, i),
+            QueueSectionMessage::MoveDown(i) => self.jobs.swap(i, i + 1),
+            QueueSectionMessage::Remove(i) => {
+                self.jobs.remove(i);
+            }
+            QueueSectionMessage::Retry(i) => {
+                if let Some(queued) = self.jobs.get_mut(i) {
+                    queued.status = JobStatus::Pending;
+                }
+            }
+            QueueSectionMessage::Cancel => {
+                if let Some(cancel) = &self.cancel {
+                    cancel.cancel();
+                }
+            }
+            QueueSectionMessage::ClearFinished => self.jobs.retain(|queued| {
+                matches!(queued.status, JobStatus::Pending | JobStatus::Running(_))
+            }),
+        }
+    }
+}
@@ -3,6 +3,10 @@ use iced::{
     Length::FillPortion,
     widget::{column, container, row, text, text_input, toggler},
 };
+use quickstitch_app::StitchJob;
+
+use crate::settings::Settings;
+use crate::stitcher::StitcherError;
 
 pub struct SettingSection {
     debug: bool,
@@ -12,18 +16,6 @@ pub struct SettingSection {
     sensitivity: Option<u8>,
 }
 
-impl Default for SettingSection {
-    fn default() -> Self {
-        Self {
-            debug: false,
-            scan_interval_field: "5".to_string(),
-            scan_interval: Some(5),
-            sensitivity_field: "255".to_string(),
-            sensitivity: Some(255),
-        }
-    }
-}
-
 #[derive(Clone, Debug)]
 pub enum SettingSectionMessage {
     SetDebugMode(bool),
@@ -32,14 +24,41 @@ pub enum SettingSectionMessage {
 }
 
 impl SettingSection {
-    pub fn scan_interval(&self) -> Option<usize> {
-        self.scan_interval
+    pub fn new(settings: &Settings) -> Self {
+        let mut section = Self {
+            debug: false,
+            scan_interval_field: String::new(),
+            scan_interval: None,
+            sensitivity_field: String::new(),
+            sensitivity: None,
+        };
+        section.apply(settings);
+        section
+    }
+    pub fn apply(&mut self, settings: &Settings) {
+        self.debug = settings.debug;
+        self.scan_interval = settings.scan_interval;
+        self.scan_interval_field = settings
+            .scan_interval
+            .map(|interval| interval.to_string())
+            .unwrap_or_default();
+        self.sensitivity = settings.sensitivity;
+        self.sensitivity_field = settings
+            .sensitivity
+            .map(|sensitivity| sensitivity.to_string())
+            .unwrap_or_default();
     }
-    pub fn sensitivity(&self) -> Option<u8> {
-        self.sensitivity
+    pub fn save(&self, settings: &mut Settings) {
+        settings.debug = self.debug;
+        settings.scan_interval = self.scan_interval;
+        settings.sensitivity = self.sensitivity;
     }
-    pub fn debug(&self) -> bool {
-        self.debug
+    /// Sets how the job looks for splitpoints.
+    pub fn configure(&self, job: &mut StitchJob) -> Result<(), StitcherError> {
+        job.scan_interval = self.scan_interval.ok_or(StitcherError::EmptyScanInterval)?;
+        job.sensitivity = self.sensitivity.ok_or(StitcherError::EmptySensitivity)?;
+        job.debug = self.debug;
+        Ok(())
     }
     pub fn view(&self) -> Element<SettingSectionMessage> {
         column![
@@ -0,0 +1,83 @@
+use iced::{
+    Background, Color, Theme,
+    widget::{button, text},
+};
+
+/// The contrast ratio WCAG asks of body text.
+const MIN_CONTRAST: f32 = 4.5;
+
+/// Text conveying an error, like [`text::danger`] but kept readable.
+///
+/// The danger color falls short of [`MIN_CONTRAST`] on the background of
+/// several built-in themes, like Dark, Nord and Gruvbox Dark, so there it is
+/// lightened or darkened until it doesn't.
+pub fn danger_text(theme: &Theme) -> text::Style {
+    let palette = theme.palette();
+    text::Style {
+        color: Some(readable(palette.danger, palette.background)),
+    }
+}
+
+/// Text conveying a warning, like [`text::warning`] but kept readable the
+/// same way as [`danger_text`]. Light themes need it most.
+pub fn warning_text(theme: &Theme) -> text::Style {
+    let palette = theme.palette();
+    text::Style {
+        color: Some(readable(palette.warning, palette.background)),
+    }
+}
+
+/// A [`button::danger`] whose label stays readable.
+///
+/// The label is already the better of black and white, but on mid-toned danger
+/// colors, like Nord's and Solarized's, neither is enough, so the background is
+/// moved away from the label instead.
+pub fn danger_button(theme: &Theme, status: button::Status) -> button::Style {
+    let style = button::danger(theme, status);
+    match style.background {
+        // Disabled buttons are faded on purpose.
+        Some(Background::Color(background)) if status != button::Status::Disabled => {
+            button::Style {
+                background: Some(Background::Color(readable(background, style.text_color))),
+                ..style
+            }
+        }
+        _ => style,
+    }
+}
+
+/// Mixes `color` in small steps towards whichever of black and white stands
+/// out more against `against`, until the two contrast enough.
+fn readable(color: Color, against: Color) -> Color {
+    let target = match contrast(Color::WHITE, against) > contrast(Color::BLACK, against) {
+        true => Color::WHITE,
+        false => Color::BLACK,
+    };
+    (0..=10)
+        .map(|step| mix(color, target, step as f32 / 10.0))
+        .find(|candidate| contrast(*candidate, against) >= MIN_CONTRAST)
+        .unwrap_or(target)
+}
+
+fn mix(a: Color, b: Color, factor: f32) -> Color {
+    let channel = |a: f32, b: f32| a + (b - a) * factor;
+    Color::from_rgb(channel(a.r, b.r), channel(a.g, b.g), channel(a.b, b.b))
+}
+
+/// The contrast ratio between two colors, as defined by WCAG.
+fn contrast(a: Color, b: Color) -> f32 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// The relative luminance of a color, as defined by WCAG.
+fn luminance(color: Color) -> f32 {
+    let linear = |channel: f32| {
+        if channel <= 0.04045 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(color.r) + 0.7152 * linear(color.g) + 0.0722 * linear(color.b)
+}
@@ -0,0 +1,163 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use iced::{
+    Element,
+    Length::FillPortion,
+    Task, clipboard,
+    widget::{button, column, row, text},
+};
+use quickstitch_app::{Skipped, Splitpoints, StitchJob, Strip};
+
+use super::{io_section::file_size, style};
+
+/// What a finished export wrote and how long each step of it took.
+#[derive(Debug, Clone)]
+pub struct Summary {
+    output: PathBuf,
+    files: Vec<PathBuf>,
+    page_heights: Vec<usize>,
+    /// The combined size of the written files, in bytes.
+    output_size: u64,
+    /// How long each step of the run took. Steps done by an earlier run, like
+    /// loading before a preview was exported, are left out.
+    phases: Vec<(&'static str, Duration)>,
+    skipped: Vec<Skipped>,
+}
+
+impl Summary {
+    pub fn new(
+        job: &StitchJob,
+        strip: &Strip,
+        splitpoints: &Splitpoints,
+        files: Vec<PathBuf>,
+        phases: Vec<(&'static str, Duration)>,
+    ) -> Self {
+        let rows: Vec<usize> = [0]
+            .into_iter()
+            .chain(splitpoints.cuts.iter().copied())
+            .chain([strip.image.height() as usize])
+            .collect();
+        Self {
+            // A new directory may have been picked if the output had files.
+            output: files
+                .first()
+                .and_then(|file| file.parent())
+                .map_or(job.output.clone(), Path::to_path_buf),
+            output_size: files
+                .iter()
+                .filter_map(|file| fs::metadata(file).ok())
+                .map(|metadata| metadata.len())
+                .sum(),
+            files,
+            page_heights: rows.windows(2).map(|page| page[1] - page[0]).collect(),
+            phases,
+            skipped: strip.skipped.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} pages written to {} ({} in {} files)",
+            self.page_heights.len(),
+            self.output.display(),
+            file_size(self.output_size),
+            self.files.len()
+        )?;
+        for (i, height) in self.page_heights.iter().enumerate() {
+            writeln!(f, "  Page {}: {height} pixels tall", i + 1)?;
+        }
+        for (phase, duration) in &self.phases {
+            writeln!(f, "{phase} took {duration:.1?}")?;
+        }
+        write!(f, "{} unloadable inputs skipped", self.skipped.len())?;
+        for skipped in &self.skipped {
+            write!(f, "\n  {}: {}", skipped.input.display(), skipped.reason)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct SummarySection {
+    summary: Option<Summary>,
+    error: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum SummarySectionMessage {
+    OpenFolder,
+    Copy,
+    Dismiss,
+}
+
+impl SummarySection {
+    pub fn set_summary(&mut self, summary: Option<Summary>) {
+        self.summary = summary;
+        self.error = None;
+    }
+    pub fn has_summary(&self) -> bool {
+        self.summary.is_some()
+    }
+    pub fn view(&self) -> Element<SummarySectionMessage> {
+        let Some(summary) = &self.summary else {
+            return column![].into();
+        };
+        column![
+            text(summary.to_string()).size(16),
+            text(self.error.as_deref().unwrap_or_default())
+                .size(16)
+                .style(style::danger_text),
+            row![
+                button(text("Open Folder").size(20))
+                    .on_press(SummarySectionMessage::OpenFolder)
+                    .width(FillPortion(1)),
+                button(text("Copy Summary").size(20))
+                    .on_press(SummarySectionMessage::Copy)
+                    .style(button::secondary)
+                    .width(FillPortion(1)),
+                button(text("Dismiss").size(20))
+                    .on_press(SummarySectionMessage::Dismiss)
+                    .style(button::secondary)
+                    .width(FillPortion(1)),
+            ]
+            .spacing(20),
+        ]
+        .spacing(10)
+        .into()
+    }
+    pub fn update(&mut self, message: SummarySectionMessage) -> Task<SummarySectionMessage> {
+        let Some(summary) = &self.summary else {
+            return Task::none();
+        };
+        match message {
+            SummarySectionMessage::OpenFolder => {
+                self.error = open_folder(&summary.output)
+                    .err()
+                    .map(|e| format!("Unable to open {}: {e}", summary.output.display()));
+            }
+            SummarySectionMessage::Copy => return clipboard::write(summary.to_string()),
+            SummarySectionMessage::Dismiss => self.summary = None,
+        }
+        Task::none()
+    }
+}
+
+/// Shows the folder in the system file manager.
+fn open_folder(path: &Path) -> std::io::Result<()> {
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    Command::new(program).arg(path).spawn().map(|_| ())
+}
@@ -6,6 +6,8 @@ use iced::{
 
 pub mod _gui;
 mod gui;
+mod presets;
+mod settings;
 mod stitcher;
 
 static ICON: &[u8] = include_bytes!("../assets/quickstitch.ico");
@@ -30,6 +32,7 @@ fn main() -> iced::Result {
         icon: Some(icon::from_rgba(icon_image.as_bytes().to_vec(), 256, 256).unwrap()),
         ..Default::default()
     })
+    .subscription(gui::Quickstitch::subscription)
     .title("Quickstitch")
     .theme(gui::Quickstitch::get_theme)
     .font(ICON_FONT)
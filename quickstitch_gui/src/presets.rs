@@ -0,0 +1,242 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use quickstitch_app::{DEFAULT_MAX_HEIGHT, DEFAULT_MIN_HEIGHT, OnExisting, OutputFormat, Sort};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use toml_edit::{DocumentMut, Item, Table};
+
+use crate::{
+    gui::limit_section::WidthType,
+    settings::{OnExistingDef, Settings, SortDef, format_name},
+};
+
+/// A named set of settings, stored in the same `[preset.<name>]` sections of
+/// `config.toml` that the CLI's `--preset` reads.
+///
+/// Only the settings the CLI understands are kept, as it rejects unknown keys.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Preset {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    on_existing: Option<OnExistingName>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sort: Option<SortName>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_height: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_height: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scan_interval: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sensitivity: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<FormatName>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quality: Option<u8>,
+    /// Like `--width`, leaving it out means the smallest input width is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name_template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    debug: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct SortName(#[serde(with = "SortDef")] Sort);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct OnExistingName(#[serde(with = "OnExistingDef")] OnExisting);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct FormatName(#[serde(with = "format_name")] OutputFormat);
+
+#[derive(Error, Debug)]
+pub enum PresetError {
+    #[error("Unable to read presets from {}: {reason}", path.display())]
+    Read { path: PathBuf, reason: String },
+    #[error("Unable to save presets to {}: {reason}", path.display())]
+    Write { path: PathBuf, reason: String },
+}
+
+impl Preset {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            output: settings.output_directory.clone(),
+            on_existing: Some(OnExistingName(settings.on_existing)),
+            sort: Some(SortName(settings.sort)),
+            max_height: settings.max_height,
+            min_height: settings.min_height,
+            scan_interval: settings.scan_interval,
+            sensitivity: settings.sensitivity,
+            format: Some(FormatName(settings.format)),
+            quality: settings.quality,
+            width: match settings.width_type {
+                WidthType::Fixed => settings.width,
+                WidthType::Auto => None,
+            },
+            name_template: Some(settings.name_template.clone()),
+            debug: Some(settings.debug),
+        }
+    }
+    /// Overwrites the settings a preset covers, with the ones it leaves out
+    /// going back to their defaults as they would for the CLI. The output
+    /// directory is only changed if the preset has one.
+    pub fn apply(&self, settings: &mut Settings) {
+        // The GUI's own defaults only differ from the CLI's in the heights.
+        let defaults = Settings {
+            max_height: Some(DEFAULT_MAX_HEIGHT),
+            min_height: Some(DEFAULT_MIN_HEIGHT),
+            ..Settings::default()
+        };
+        if let Some(output) = &self.output {
+            settings.output_directory = Some(output.clone());
+        }
+        settings.on_existing = self
+            .on_existing
+            .map_or(defaults.on_existing, |OnExistingName(on_existing)| {
+                on_existing
+            });
+        settings.sort = self.sort.map_or(defaults.sort, |SortName(sort)| sort);
+        settings.max_height = self.max_height.or(defaults.max_height);
+        settings.min_height = self.min_height.or(defaults.min_height);
+        settings.scan_interval = self.scan_interval.or(defaults.scan_interval);
+        settings.sensitivity = self.sensitivity.or(defaults.sensitivity);
+        settings.format = self
+            .format
+            .map_or(defaults.format, |FormatName(format)| format);
+        settings.quality = self.quality.or(defaults.quality);
+        match self.width {
+            Some(width) => {
+                settings.width_type = WidthType::Fixed;
+                settings.width = Some(width);
+            }
+            None => settings.width_type = WidthType::Auto,
+        }
+        settings.name_template = self.name_template.clone().unwrap_or(defaults.name_template);
+        settings.debug = self.debug.unwrap_or(defaults.debug);
+    }
+}
+
+/// The user's CLI config file, which the presets are kept in.
+pub fn presets_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("quickstitch").join("config.toml"))
+}
+
+/// Reads the `[preset.<name>]` sections of a config file.
+pub fn load(path: &Path) -> Result<BTreeMap<String, Preset>, PresetError> {
+    let Some(mut table) = read_table(path)? else {
+        return Ok(BTreeMap::new());
+    };
+    match table.remove("preset") {
+        Some(presets) => presets
+            .try_into()
+            .map_err(|e: toml::de::Error| read_error(path, e.to_string())),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+/// Reads the presets in a file chosen for import.
+///
+/// A file without any `[preset.<name>]` sections is read as a single preset
+/// named after the file, so any config file can be imported.
+pub fn import(path: &Path) -> Result<BTreeMap<String, Preset>, PresetError> {
+    let Some(table) = read_table(path)? else {
+        return Ok(BTreeMap::new());
+    };
+    if table.contains_key("preset") || table.is_empty() {
+        return load(path);
+    }
+    let name = path
+        .file_stem()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .to_string();
+    let preset = toml::Value::Table(table)
+        .try_into()
+        .map_err(|e: toml::de::Error| read_error(path, e.to_string()))?;
+    Ok(BTreeMap::from([(name, preset)]))
+}
+
+/// Replaces the presets in a config file, leaving the rest of the file,
+/// comments and formatting included, as it was. Presets that haven't changed
+/// are left untouched too.
+pub fn save(path: &Path, presets: &BTreeMap<String, Preset>) -> Result<(), PresetError> {
+    let write_error = |reason: String| PresetError::Write {
+        path: path.to_path_buf(),
+        reason,
+    };
+    let mut document = match fs::read_to_string(path) {
+        Ok(contents) => contents
+            .parse::<DocumentMut>()
+            .map_err(|e| read_error(path, e.to_string()))?,
+        Err(e) if e.kind() == ErrorKind::NotFound => DocumentMut::new(),
+        Err(e) => return Err(read_error(path, e.to_string())),
+    };
+    if presets.is_empty() {
+        document.remove("preset");
+    } else {
+        // A preset that no longer reads back the same is written again.
+        let saved = load(path).unwrap_or_default();
+        let table = document
+            .entry("preset")
+            .or_insert_with(|| {
+                let mut table = Table::new();
+                table.set_implicit(true);
+                Item::Table(table)
+            })
+            .as_table_mut()
+            .ok_or_else(|| write_error("`preset` is not a table".to_string()))?;
+        table.retain(|name, _| presets.contains_key(name));
+        for (name, preset) in presets {
+            if saved.get(name) == Some(preset) {
+                continue;
+            }
+            let preset =
+                toml_edit::ser::to_document(preset).map_err(|e| write_error(e.to_string()))?;
+            table.insert(name, Item::Table(preset.as_table().clone()));
+        }
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| write_error(e.to_string()))?;
+    }
+    fs::write(path, document.to_string()).map_err(|e| write_error(e.to_string()))
+}
+
+/// Writes a single preset to a new file that can be imported again or given
+/// to the CLI as its config.
+pub fn export(path: &Path, name: &str, preset: &Preset) -> Result<(), PresetError> {
+    if let Err(e) = fs::remove_file(path)
+        && e.kind() != ErrorKind::NotFound
+    {
+        return Err(PresetError::Write {
+            path: path.to_path_buf(),
+            reason: e.to_string(),
+        });
+    }
+    save(path, &BTreeMap::from([(name.to_string(), preset.clone())]))
+}
+
+fn read_table(path: &Path) -> Result<Option<toml::Table>, PresetError> {
+    match fs::read_to_string(path) {
+        Ok(contents) => toml::from_str(&contents)
+            .map(Some)
+            .map_err(|e| read_error(path, e.to_string())),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(read_error(path, e.to_string())),
+    }
+}
+
+fn read_error(path: &Path, reason: String) -> PresetError {
+    PresetError::Read {
+        path: path.to_path_buf(),
+        reason,
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use quickstitch_app::{
+    DEFAULT_NAME_TEMPLATE, DEFAULT_SCAN_INTERVAL, DEFAULT_SENSITIVITY, OnExisting, OutputFormat,
+    Sort,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::gui::{io_section::InputType, limit_section::WidthType};
+
+/// How many recently used directories are remembered for each field.
+const MAX_RECENT: usize = 10;
+
+/// Everything the GUI remembers between sessions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Settings {
+    pub input_type: InputType,
+    #[serde(with = "SortDef")]
+    pub sort: Sort,
+    pub ignore_unloadable: bool,
+    pub auto_restitch: bool,
+    pub output_directory: Option<PathBuf>,
+    #[serde(with = "OnExistingDef")]
+    pub on_existing: OnExisting,
+    #[serde(with = "format_name")]
+    pub format: OutputFormat,
+    pub quality: Option<u8>,
+    pub name_template: String,
+    pub pack_cbz: bool,
+    pub width_type: WidthType,
+    pub width: Option<u32>,
+    pub max_height: Option<usize>,
+    pub min_height: Option<usize>,
+    pub scan_interval: Option<usize>,
+    pub sensitivity: Option<u8>,
+    pub debug: bool,
+    /// The name of the chosen built-in theme, or none to follow the system.
+    pub theme: Option<String>,
+    /// Input directories and archives, most recent first.
+    pub recent_inputs: Vec<PathBuf>,
+    /// Output directories, most recent first.
+    pub recent_outputs: Vec<PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            input_type: InputType::default(),
+            sort: Sort::default(),
+            ignore_unloadable: true,
+            auto_restitch: false,
+            output_directory: None,
+            on_existing: OnExisting::default(),
+            format: OutputFormat::Jpg(100),
+            quality: Some(100),
+            name_template: DEFAULT_NAME_TEMPLATE.to_string(),
+            pack_cbz: false,
+            width_type: WidthType::default(),
+            width: Some(800),
+            max_height: Some(15000),
+            min_height: Some(10000),
+            scan_interval: Some(DEFAULT_SCAN_INTERVAL),
+            sensitivity: Some(DEFAULT_SENSITIVITY),
+            debug: false,
+            theme: None,
+            recent_inputs: vec![],
+            recent_outputs: vec![],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Sort", rename_all = "lowercase")]
+pub(crate) enum SortDef {
+    Natural,
+    Logical,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "OnExisting", rename_all = "kebab-case")]
+pub(crate) enum OnExistingDef {
+    Error,
+    Overwrite,
+    Clean,
+    NewDir,
+}
+
+/// Reads and writes an [`OutputFormat`] by its extension, like the CLI's
+/// `--format`. The JPEG quality is kept in its own setting, so JPEG always
+/// reads back with a quality of 100.
+pub(crate) mod format_name {
+    use quickstitch_app::OutputFormat;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    const FORMATS: &[&str] = &["jpg", "jpeg", "webp", "png"];
+
+    pub fn serialize<S: Serializer>(
+        format: &OutputFormat,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(format.extension())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<OutputFormat, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "jpg" => Ok(OutputFormat::Jpg(100)),
+            "jpeg" => Ok(OutputFormat::Jpeg(100)),
+            "webp" => Ok(OutputFormat::Webp),
+            "png" => Ok(OutputFormat::Png),
+            other => Err(D::Error::unknown_variant(other, FORMATS)),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SettingsError {
+    #[error("Unable to read settings from {}: {reason}", path.display())]
+    Read { path: PathBuf, reason: String },
+    #[error("Unable to save settings to {}: {reason}", path.display())]
+    Write { path: PathBuf, reason: String },
+}
+
+/// Where the settings are kept, if the platform has a config directory.
+pub fn settings_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("quickstitch").join("gui.toml"))
+}
+
+/// Reads the saved settings, falling back to the defaults when none have been
+/// saved yet.
+pub fn load(path: &Path) -> Result<Settings, SettingsError> {
+    let read_error = |reason: String| SettingsError::Read {
+        path: path.to_path_buf(),
+        reason,
+    };
+    match fs::read_to_string(path) {
+        Ok(contents) => toml::from_str(&contents).map_err(|e| read_error(e.to_string())),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Settings::default()),
+        Err(e) => Err(read_error(e.to_string())),
+    }
+}
+
+pub fn save(path: &Path, settings: &Settings) -> Result<(), SettingsError> {
+    let write_error = |reason: String| SettingsError::Write {
+        path: path.to_path_buf(),
+        reason,
+    };
+    let contents = toml::to_string(settings).map_err(|e| write_error(e.to_string()))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| write_error(e.to_string()))?;
+    }
+    fs::write(path, contents).map_err(|e| write_error(e.to_string()))
+}
+
+/// Moves the path to the front of the list, dropping the oldest entries.
+pub fn remember(recent: &mut Vec<PathBuf>, path: &Path) {
+    recent.retain(|recent| recent != path);
+    recent.insert(0, path.to_path_buf());
+    recent.truncate(MAX_RECENT);
+}
@@ -0,0 +1,149 @@
+use std::{sync::Arc, thread, time::Instant};
+
+use iced::futures::{Stream, channel::mpsc};
+use quickstitch_app::{CancelToken, Progress, Splitpoints, StitchError, StitchJob, Strip};
+use thiserror::Error;
+
+use crate::gui::{preview_section::Preview, summary_section::Summary};
+
+mod watch;
+
+pub use watch::watch;
+
+/// Settings that are missing or can't be turned into a [`StitchJob`].
+#[derive(Error, Debug, Clone)]
+pub enum StitcherError {
+    #[error("Input directory must be set")]
+    NoInputDirectory,
+    #[error("Input archive must be set")]
+    NoInputArchive,
+    #[error("Output directory must be set")]
+    NoOutputDirectory,
+    #[error("Compression quality cannot be empty")]
+    EmptyQuality,
+    #[error("Output image width cannot be empty")]
+    EmptyOutputImageWidth,
+    #[error("Max output height cannot be empty")]
+    EmptyMaxOutputHeight,
+    #[error("Min output height cannot be empty")]
+    EmptyMinOutputHeight,
+    #[error("Scan interval cannot be empty")]
+    EmptyScanInterval,
+    #[error("Sensitivity cannot be empty")]
+    EmptySensitivity,
+    #[error(transparent)]
+    Invalid(#[from] StitchError),
+}
+
+/// Progress reported by a running stitch.
+#[derive(Debug, Clone)]
+pub enum StitchProgress {
+    Running(Progress),
+    /// The splitpoints have been found, but nothing has been exported yet.
+    Previewed(Preview),
+    /// The pages have been written.
+    Exported(Summary),
+    Finished(Result<Splitpoints, StitchError>),
+}
+
+/// Runs the job on a background thread, streaming its progress.
+///
+/// The last item of the stream is always [`StitchProgress::Finished`].
+pub fn stitch(job: StitchJob, cancel: CancelToken) -> impl Stream<Item = StitchProgress> {
+    spawn(move |sender| load_and_cut(&job, &cancel, true, sender))
+}
+
+/// Like [`stitch`], but stops once the splitpoints have been found, without
+/// exporting anything.
+pub fn preview(job: StitchJob, cancel: CancelToken) -> impl Stream<Item = StitchProgress> {
+    spawn(move |sender| load_and_cut(&job, &cancel, false, sender))
+}
+
+/// Like [`stitch`], but without building a preview, for jobs run from the
+/// queue.
+pub fn stitch_queued(job: StitchJob, cancel: CancelToken) -> impl Stream<Item = StitchProgress> {
+    spawn(move |sender| {
+        let outcome = quickstitch_app::run(&job, &cancel, &progress(sender))?;
+        Ok(Splitpoints {
+            cuts: outcome.splitpoints,
+            ..Default::default()
+        })
+    })
+}
+
+/// Finds the splitpoints of an already loaded strip again using the job's
+/// settings.
+pub fn detect(job: StitchJob, strip: Arc<Strip>) -> impl Stream<Item = StitchProgress> {
+    spawn(move |sender| {
+        progress(sender)(Progress::FindingSplitpoints);
+        Ok(quickstitch_app::find_splitpoints(&job, &strip))
+    })
+}
+
+/// Exports an already loaded strip cut exactly at the given splitpoints.
+pub fn export(
+    job: StitchJob,
+    strip: Arc<Strip>,
+    splitpoints: Splitpoints,
+    cancel: CancelToken,
+) -> impl Stream<Item = StitchProgress> {
+    spawn(move |sender| {
+        let started = Instant::now();
+        let files =
+            quickstitch_app::export(&job, &strip, &splitpoints, &cancel, &progress(sender))?;
+        let phases = vec![("Exporting", started.elapsed())];
+        let summary = Summary::new(&job, &strip, &splitpoints, files, phases);
+        let _ = sender.unbounded_send(StitchProgress::Exported(summary));
+        Ok(splitpoints)
+    })
+}
+
+fn spawn(
+    run: impl FnOnce(&Sender) -> Result<Splitpoints, StitchError> + Send + 'static,
+) -> impl Stream<Item = StitchProgress> {
+    let (sender, receiver) = mpsc::unbounded();
+    thread::spawn(move || {
+        let result = run(&sender);
+        let _ = sender.unbounded_send(StitchProgress::Finished(result));
+    });
+    receiver
+}
+
+type Sender = mpsc::UnboundedSender<StitchProgress>;
+
+fn progress(sender: &Sender) -> impl Fn(Progress) + Sync + '_ {
+    |progress| {
+        let _ = sender.unbounded_send(StitchProgress::Running(progress));
+    }
+}
+
+fn load_and_cut(
+    job: &StitchJob,
+    cancel: &CancelToken,
+    export: bool,
+    sender: &Sender,
+) -> Result<Splitpoints, StitchError> {
+    let progress = progress(sender);
+    let started = Instant::now();
+    let strip = Arc::new(quickstitch_app::load(job, cancel, &progress)?);
+    if cancel.is_cancelled() {
+        return Err(StitchError::Cancelled);
+    }
+    let mut phases = vec![("Loading", started.elapsed())];
+    progress(Progress::FindingSplitpoints);
+    let started = Instant::now();
+    let splitpoints = quickstitch_app::find_splitpoints(job, &strip);
+    phases.push(("Finding splitpoints", started.elapsed()));
+    let _ = sender.unbounded_send(StitchProgress::Previewed(Preview::new(
+        strip.clone(),
+        splitpoints.clone(),
+    )));
+    if export {
+        let started = Instant::now();
+        let files = quickstitch_app::export(job, &strip, &splitpoints, cancel, &progress)?;
+        phases.push(("Exporting", started.elapsed()));
+        let summary = Summary::new(job, &strip, &splitpoints, files, phases);
+        let _ = sender.unbounded_send(StitchProgress::Exported(summary));
+    }
+    Ok(splitpoints)
+}
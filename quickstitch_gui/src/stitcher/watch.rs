@@ -0,0 +1,72 @@
+use std::{path::PathBuf, time::Duration};
+
+use iced::futures::{Stream, StreamExt, channel::mpsc, stream};
+use notify_debouncer_full::{
+    DebounceEventResult, new_debouncer,
+    notify::{EventKind, RecursiveMode},
+};
+
+use quickstitch_app::is_image;
+
+/// How long the input directory has to stay unchanged before a change is
+/// reported.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Emits an item whenever images in the input directory are added, changed or
+/// removed, once the changes have settled, or an error if watching fails.
+///
+/// Images being read are ignored, so stitching doesn't trigger another stitch.
+/// Watching stops when the stream is dropped, or right after the error if the
+/// directory couldn't be watched at all.
+pub fn watch(input: &PathBuf) -> impl Stream<Item = Result<(), String>> + use<> {
+    let (sender, receiver) = mpsc::unbounded();
+    let events = sender.clone();
+    let dir = input.clone();
+    let debouncer = new_debouncer(
+        DEBOUNCE,
+        None,
+        move |result: DebounceEventResult| match result {
+            Ok(changes) => {
+                if changes
+                    .iter()
+                    .filter(|event| !matches!(event.kind, EventKind::Access(_)))
+                    .flat_map(|event| &event.paths)
+                    .any(|path| is_image(path))
+                {
+                    let _ = events.unbounded_send(Ok(()));
+                }
+            }
+            Err(errors) => {
+                for e in errors {
+                    let _ = events.unbounded_send(Err(format!(
+                        "Error while watching {}: {e}",
+                        dir.display()
+                    )));
+                }
+            }
+        },
+    )
+    .and_then(|mut debouncer| {
+        debouncer.watch(input, RecursiveMode::NonRecursive)?;
+        Ok(debouncer)
+    });
+    let debouncer = match debouncer {
+        Ok(debouncer) => Some(debouncer),
+        Err(e) => {
+            let _ = sender.unbounded_send(Err(format!("Unable to watch {}: {e}", input.display())));
+            None
+        }
+    };
+    // Without a debouncer nothing else holds a sender, so the stream ends
+    // after the error.
+    drop(sender);
+    // The debouncer stops watching once dropped, so it lives as long as the
+    // stream does.
+    stream::unfold(
+        (debouncer, receiver),
+        |(debouncer, mut receiver)| async move {
+            let item = receiver.next().await?;
+            Some((item, (debouncer, receiver)))
+        },
+    )
+}